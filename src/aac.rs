@@ -0,0 +1,445 @@
+// RTP Payload Formats for MPEG-4 AAC audio
+//
+// mpeg4-generic, AAC-hbr mode
+// https://tools.ietf.org/html/rfc3640#section-3.3.6
+//
+// +---------+-----------+-----------+---------------+
+// | RTP     | AU Header | Auxiliary | Access Unit   |
+// | Header  | Section   | Section   | Data Section  |
+// +---------+-----------+-----------+---------------+
+//
+// MP4A-LATM, with the StreamMuxConfig out of band (`cpresent=0`)
+// https://tools.ietf.org/html/rfc6416#section-6.1
+//
+// AudioSpecificConfig is ISO/IEC 14496-3 1.6.2.1.
+
+use std::collections::VecDeque;
+
+use rtp;
+use sdp::Fmtp;
+use media::{ AudioFrame, Depacketizer, Sequence };
+use error::{ Error, Result };
+
+const SAMPLE_RATES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioSpecificConfig {
+    /// 2 for AAC LC, 5 for SBR, 29 for PS.
+    pub object_type   : u8,
+    /// The sampling rate of the core AAC decoder.
+    pub sample_rate   : u32,
+    /// The output rate when SBR is signalled explicitly.
+    pub extension_rate: Option<u32>,
+    /// The channel configuration, 0 when a program config element has it.
+    pub channels      : u8,
+    /// Samples per frame at `sample_rate`, 1024 or 960.
+    pub frame_length  : u16
+}
+
+/// Depacketizes `mpeg4-generic` streams in AAC-hbr or AAC-lbr mode.
+#[derive(Debug, Clone)]
+pub struct Mpeg4Generic {
+    config            : AudioSpecificConfig,
+    // The RTP timestamps between two frames.
+    duration          : u32,
+    size_length       : u32,
+    index_length      : u32,
+    index_delta_length: u32,
+    cts_delta_length  : u32,
+    dts_delta_length  : u32,
+    random_access     : bool,
+    stream_state      : u32,
+    auxiliary_length  : u32,
+    sequence          : Sequence,
+    // The timestamp, full size and data so far of a fragmented AU.
+    fragment          : Option<(u32, usize, Vec<u8>)>,
+    loss              : u32,
+    ready             : VecDeque<AudioFrame>
+}
+
+/// Depacketizes `MP4A-LATM` streams.
+#[derive(Debug, Clone)]
+pub struct Latm {
+    config    : AudioSpecificConfig,
+    duration  : u32,
+    sub_frames: u8,
+    sequence  : Sequence,
+    timestamp : u32,
+    buf       : Vec<u8>,
+    loss      : u32,
+    ready     : VecDeque<AudioFrame>
+}
+
+/// Reads bit fields most significant bit first.
+struct Bits<'a> {
+    buf: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Bits<'a> {
+    fn new (buf: &'a [u8]) -> Bits<'a> {
+        Bits { buf, pos: 0 }
+    }
+    fn read(&mut self, n: u32) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..n {
+            let byte = *self.buf.get(self.pos / 8).ok_or_else(|| invalid("AAC bit field exceeds the data"))?;
+            value = value << 1 | u32::from(byte >> (7 - self.pos % 8) & 1);
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+}
+
+impl AudioSpecificConfig {
+    pub fn parse(buf: &[u8]) -> Result<AudioSpecificConfig> {
+        AudioSpecificConfig::read(&mut Bits::new(buf))
+    }
+    fn read(r: &mut Bits) -> Result<AudioSpecificConfig> {
+        let mut object_type = read_object_type(r)?;
+        let sample_rate = read_sample_rate(r)?;
+        let channels = r.read(4)? as u8;
+        let mut extension_rate = None;
+        if object_type == 5 || object_type == 29 {
+            extension_rate = Some(read_sample_rate(r)?);
+            object_type = read_object_type(r)?;
+        }
+        let mut frame_length = 1024;
+        match object_type {
+            1 | 2 | 3 | 4 | 6 | 7 | 17 | 19 | 20 | 21 | 22 | 23 => {
+                // GASpecificConfig
+                if r.read(1)? == 1 {
+                    frame_length = 960;
+                }
+                if r.read(1)? == 1 {
+                    r.read(14)?;
+                }
+                let extension = r.read(1)? == 1;
+                if channels == 0 {
+                    return Err(invalid("AAC program config elements are not supported"))
+                }
+                if object_type == 6 || object_type == 20 {
+                    r.read(3)?;
+                }
+                if extension {
+                    if object_type == 22 {
+                        r.read(16)?;
+                    }
+                    if object_type == 17 || object_type >= 19 {
+                        r.read(3)?;
+                    }
+                    r.read(1)?;
+                }
+            },
+            _ => return Err(invalid("Unsupported MPEG-4 audio object type"))
+        }
+        if (object_type == 17 || object_type >= 19) && r.read(2)? > 1 {
+            return Err(invalid("Unsupported AAC epConfig"))
+        }
+        Ok(AudioSpecificConfig { object_type, sample_rate, extension_rate, channels, frame_length })
+    }
+    /// The RTP timestamps a frame lasts at `clock_rate`.
+    pub fn duration(&self, clock_rate: u32) -> u32 {
+        (u64::from(self.frame_length) * u64::from(clock_rate) / u64::from(self.sample_rate.max(1))) as u32
+    }
+}
+
+impl Mpeg4Generic {
+    /// Reads the AU header layout and `config` from `a=fmtp`, for a
+    /// stream whose `a=rtpmap` clock rate is `clock_rate`.
+    pub fn from_fmtp(fmtp: &Fmtp, clock_rate: u32) -> Result<Mpeg4Generic> {
+        match fmtp.get("mode") {
+            Some(mode) if mode.eq_ignore_ascii_case("AAC-hbr") || mode.eq_ignore_ascii_case("AAC-lbr") => (),
+            _ => return Err(invalid("mpeg4-generic mode is not AAC-hbr or AAC-lbr"))
+        }
+        let config = AudioSpecificConfig::parse(&hex_config(fmtp)?)?;
+        let number = |name: &str| -> Result<u32> {
+            match fmtp.get(name) {
+                Some(value) => value.trim().parse().map_err(|_| invalid("Invalid mpeg4-generic parameter")),
+                None => Ok(0)
+            }
+        };
+        // Field widths in bits, read into a u32.
+        let length = |name: &str| -> Result<u32> {
+            match number(name)? {
+                bits if bits <= 32 => Ok(bits),
+                _ => Err(invalid("mpeg4-generic field is longer than 32 bits"))
+            }
+        };
+        let size_length = number("sizeLength")?;
+        if size_length == 0 || size_length > 16 {
+            return Err(invalid("Invalid mpeg4-generic sizeLength"))
+        }
+        Ok(Mpeg4Generic {
+            config,
+            duration: config.duration(clock_rate),
+            size_length,
+            index_length: length("indexLength")?,
+            index_delta_length: length("indexDeltaLength")?,
+            cts_delta_length: length("CTSDeltaLength")?,
+            dts_delta_length: length("DTSDeltaLength")?,
+            random_access: number("randomAccessIndication")? == 1,
+            stream_state: length("streamStateIndication")?,
+            auxiliary_length: length("auxiliaryDataSizeLength")?,
+            sequence: Sequence::new(),
+            fragment: None,
+            loss: 0,
+            ready: VecDeque::new()
+        })
+    }
+    pub fn config(&self) -> &AudioSpecificConfig {
+        &self.config
+    }
+}
+
+impl Depacketizer for Mpeg4Generic {
+    type Frame = AudioFrame;
+
+    fn push(&mut self, packet: &rtp::Packet) -> Result<()> {
+        let lost = match self.sequence.next(packet.sequence_number()) {
+            Some(lost) => lost,
+            None => return Ok(())
+        };
+        self.loss += lost;
+        let payload = packet.payload();
+        if payload.len() < 2 {
+            return Err(invalid("mpeg4-generic payload is too short"))
+        }
+        let headers_bits = u32::from(u16::from_be_bytes([payload[0], payload[1]]));
+        let headers_end = 2 + (headers_bits as usize).div_ceil(8);
+        let mut r = Bits::new(payload.get(2..headers_end).ok_or_else(|| invalid("AU headers exceed the packet"))?);
+        let mut headers = Vec::new();
+        let mut index = 0;
+        while (r.pos as u32) < headers_bits {
+            let size = r.read(self.size_length)? as usize;
+            index = if headers.is_empty() {
+                r.read(self.index_length)?
+            } else {
+                r.read(self.index_delta_length)?.checked_add(1).and_then(|delta| index.checked_add(delta))
+                     .ok_or_else(|| invalid("AU index overflows"))?
+            };
+            if self.cts_delta_length > 0 && r.read(1)? == 1 {
+                r.read(self.cts_delta_length)?;
+            }
+            if self.dts_delta_length > 0 && r.read(1)? == 1 {
+                r.read(self.dts_delta_length)?;
+            }
+            if self.random_access {
+                r.read(1)?;
+            }
+            r.read(self.stream_state)?;
+            headers.push((size, index));
+        }
+        let mut data = &payload[headers_end..];
+        if self.auxiliary_length > 0 {
+            let mut r = Bits::new(data);
+            let bits = r.read(self.auxiliary_length)? as usize;
+            data = data.get((self.auxiliary_length as usize + bits).div_ceil(8)..)
+                       .ok_or_else(|| invalid("Auxiliary section exceeds the packet"))?;
+        }
+
+        // The rest of a fragmented AU, with the same timestamp and header.
+        if let Some((timestamp, size, mut buf)) = self.fragment.take() {
+            if lost == 0 && timestamp == packet.timestamp() && headers.len() == 1 && headers[0].0 == size {
+                buf.extend_from_slice(data);
+                if buf.len() < size {
+                    self.fragment = Some((timestamp, size, buf));
+                } else {
+                    buf.truncate(size);
+                    self.ready.push_back(AudioFrame { timestamp, data: buf, loss: ::std::mem::take(&mut self.loss) });
+                }
+                return Ok(())
+            }
+            // The AU lost its end and is left out.
+        }
+        let first = headers.first().map(|&(_, index)| index).unwrap_or(0);
+        for &(size, index) in &headers {
+            let timestamp = packet.timestamp().wrapping_add(index.wrapping_sub(first).wrapping_mul(self.duration));
+            if data.len() < size {
+                if headers.len() == 1 {
+                    self.fragment = Some((timestamp, size, data.to_vec()));
+                    return Ok(())
+                }
+                return Err(invalid("Access unit exceeds the packet"))
+            }
+            self.ready.push_back(AudioFrame { timestamp, data: data[..size].to_vec(), loss: ::std::mem::take(&mut self.loss) });
+            data = &data[size..];
+        }
+        Ok(())
+    }
+    fn pull(&mut self) -> Option<AudioFrame> {
+        self.ready.pop_front()
+    }
+}
+
+impl Latm {
+    /// Reads the StreamMuxConfig from `a=fmtp`, for a stream whose
+    /// `a=rtpmap` clock rate is `clock_rate`.
+    pub fn from_fmtp(fmtp: &Fmtp, clock_rate: u32) -> Result<Latm> {
+        if fmtp.get("cpresent").map(str::trim).unwrap_or("1") != "0" {
+            return Err(invalid("In band LATM configuration is not supported"))
+        }
+        let config = hex_config(fmtp)?;
+        let mut r = Bits::new(&config);
+        if r.read(1)? != 0 {
+            return Err(invalid("Unsupported LATM audioMuxVersion"))
+        }
+        if r.read(1)? != 1 {
+            return Err(invalid("LATM streams with different time framing are not supported"))
+        }
+        let sub_frames = r.read(6)? as u8;
+        if r.read(4)? != 0 || r.read(3)? != 0 {
+            return Err(invalid("LATM with more than one program or layer is not supported"))
+        }
+        let asc = AudioSpecificConfig::read(&mut r)?;
+        if r.read(3)? != 0 {
+            return Err(invalid("Unsupported LATM frameLengthType"))
+        }
+        Ok(Latm {
+            config: asc,
+            duration: asc.duration(clock_rate),
+            sub_frames,
+            sequence: Sequence::new(),
+            timestamp: 0,
+            buf: Vec::new(),
+            loss: 0,
+            ready: VecDeque::new()
+        })
+    }
+    pub fn config(&self) -> &AudioSpecificConfig {
+        &self.config
+    }
+}
+
+impl Depacketizer for Latm {
+    type Frame = AudioFrame;
+
+    fn push(&mut self, packet: &rtp::Packet) -> Result<()> {
+        let lost = match self.sequence.next(packet.sequence_number()) {
+            Some(lost) => lost,
+            None => return Ok(())
+        };
+        self.loss += lost;
+        if !self.buf.is_empty() && (lost > 0 || packet.timestamp() != self.timestamp) {
+            // A muxed element split over packets lost its end.
+            self.buf.clear();
+        }
+        self.timestamp = packet.timestamp();
+        self.buf.extend_from_slice(packet.payload());
+        if !packet.marker() {
+            return Ok(())
+        }
+        let buf = ::std::mem::take(&mut self.buf);
+        let mut rest = &buf[..];
+        for i in 0..u32::from(self.sub_frames) + 1 {
+            // PayloadLengthInfo: bytes of 255 continue the length.
+            let mut len = 0;
+            loop {
+                let (&byte, tail) = rest.split_first().ok_or_else(|| invalid("LATM length exceeds the packet"))?;
+                rest = tail;
+                len += byte as usize;
+                if byte != 255 {
+                    break;
+                }
+            }
+            if rest.len() < len {
+                return Err(invalid("LATM frame exceeds the packet"))
+            }
+            self.ready.push_back(AudioFrame {
+                timestamp: self.timestamp.wrapping_add(i * self.duration),
+                data     : rest[..len].to_vec(),
+                loss     : ::std::mem::take(&mut self.loss)
+            });
+            rest = &rest[len..];
+        }
+        Ok(())
+    }
+    fn pull(&mut self) -> Option<AudioFrame> {
+        self.ready.pop_front()
+    }
+}
+
+fn read_object_type(r: &mut Bits) -> Result<u8> {
+    match r.read(5)? {
+        31 => Ok(32 + r.read(6)? as u8),
+        object_type => Ok(object_type as u8)
+    }
+}
+
+fn read_sample_rate(r: &mut Bits) -> Result<u32> {
+    match r.read(4)? {
+        15 => r.read(24),
+        index => SAMPLE_RATES.get(index as usize).cloned().ok_or_else(|| invalid("Invalid AAC sampling frequency index"))
+    }
+}
+
+/// The hex `config` parameter of `a=fmtp`.
+fn hex_config(fmtp: &Fmtp) -> Result<Vec<u8>> {
+    let config = fmtp.get("config").map(str::trim).ok_or_else(|| invalid("Missing AAC config"))?;
+    if config.len() % 2 != 0 {
+        return Err(invalid("Invalid AAC config"))
+    }
+    (0..config.len()).step_by(2)
+                     .map(|i| config.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                     .collect::<Option<Vec<u8>>>()
+                     .ok_or_else(|| invalid("Invalid AAC config"))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Media(reason.to_string())
+}
+
+
+
+#[test]
+fn test() {
+    let push = |depacketizer: &mut dyn Depacketizer<Frame = AudioFrame>, seq: u16, timestamp: u32, marker: bool, payload: &[u8]| {
+        let mut builder = rtp::PacketBuilder::new(97, seq, timestamp, 1);
+        builder.marker = marker;
+        let buf = builder.build(payload).unwrap();
+        depacketizer.push(&rtp::Packet::parse(&buf).unwrap()).unwrap();
+        depacketizer.pull()
+    };
+
+    // AAC LC, 16 kHz, stereo.
+    let fmtp: Fmtp = "97 streamtype=5; profile-level-id=15; mode=AAC-hbr; config=1410; SizeLength=13; IndexLength=3; IndexDeltaLength=3".parse().unwrap();
+    let mut generic = Mpeg4Generic::from_fmtp(&fmtp, 16000).unwrap();
+    assert_eq!(*generic.config(), AudioSpecificConfig { object_type: 2, sample_rate: 16000, extension_rate: None, channels: 2, frame_length: 1024 });
+    // Two AUs of 2 and 3 bytes.
+    let payload = [0x00, 0x20, 0x00, 0x10, 0x00, 0x18, 1, 2, 3, 4, 5];
+    assert_eq!(push(&mut generic, 1, 5000, true, &payload).unwrap(), AudioFrame { timestamp: 5000, data: vec![1, 2], loss: 0 });
+    assert_eq!(generic.pull().unwrap(), AudioFrame { timestamp: 6024, data: vec![3, 4, 5], loss: 0 });
+    // One AU of 4 bytes in two fragments.
+    assert_eq!(push(&mut generic, 2, 7048, false, &[0x00, 0x10, 0x00, 0x20, 1, 2]), None);
+    assert_eq!(push(&mut generic, 3, 7048, true, &[0x00, 0x10, 0x00, 0x20, 3, 4]).unwrap().data, vec![1, 2, 3, 4]);
+    assert_eq!(push(&mut generic, 5, 9096, true, &[0x00, 0x10, 0x00, 0x08, 9]).unwrap().loss, 1);
+    // A fragmented AU that loses a packet is dropped, and the loss is the
+    // one packet.
+    assert_eq!(push(&mut generic, 6, 10120, false, &[0x00, 0x10, 0x00, 0x20, 1, 2]), None);
+    assert_eq!(push(&mut generic, 8, 12168, true, &[0x00, 0x10, 0x00, 0x08, 9]).unwrap(), AudioFrame { timestamp: 12168, data: vec![9], loss: 1 });
+
+    // An index that would overflow with the next AU, and too wide a field.
+    let fmtp: Fmtp = "97 mode=AAC-hbr; config=1410; sizeLength=13; indexLength=32; indexDeltaLength=0".parse().unwrap();
+    let mut generic = Mpeg4Generic::from_fmtp(&fmtp, 16000).unwrap();
+    let buf = rtp::PacketBuilder::new(97, 1, 0, 1).build(&[0x00, 0x3a, 0x00, 0x0f, 0xff, 0xff, 0xff, 0xf8, 0x00, 0x40, 1, 2]).unwrap();
+    match generic.push(&rtp::Packet::parse(&buf).unwrap()) {
+        Err(Error::Media(_)) => (),
+        other => panic!("{:?}", other)
+    }
+    let fmtp: Fmtp = "97 mode=AAC-hbr; config=1410; sizeLength=13; indexLength=33".parse().unwrap();
+    assert!(Mpeg4Generic::from_fmtp(&fmtp, 16000).is_err());
+
+    // HE-AAC with explicit SBR: 24 kHz core, 48 kHz output, mono, in LATM.
+    let asc = AudioSpecificConfig::parse(&[0x2b, 0x09, 0x88, 0x00]).unwrap();
+    assert_eq!((asc.object_type, asc.sample_rate, asc.extension_rate, asc.channels), (2, 24000, Some(48000), 1));
+    let fmtp: Fmtp = "96 profile-level-id=1; bitrate=64000; cpresent=0; object=2; config=400026203fc0".parse().unwrap();
+    let mut latm = Latm::from_fmtp(&fmtp, 24000).unwrap();
+    assert_eq!(latm.config().sample_rate, 24000);
+    assert_eq!(latm.config().channels, 2);
+    let mut frame = vec![255, 1];
+    frame.extend_from_slice(&[7; 256]);
+    assert_eq!(push(&mut latm, 1, 100, false, &frame[..100]), None);
+    let out = push(&mut latm, 2, 100, true, &frame[100..]).unwrap();
+    assert_eq!(out.data, vec![7; 256]);
+    assert_eq!(out.timestamp, 100);
+}
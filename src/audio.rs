@@ -0,0 +1,309 @@
+// RTP Payload Formats for simple audio codecs
+//
+// PCMU, PCMA, G722 and L16 of the audio/video profile
+// https://tools.ietf.org/html/rfc3551#section-4.5
+//
+// Opus
+// https://tools.ietf.org/html/rfc7587
+//
+// Each packet holds whole frames; the sample based codecs may be split at
+// any sample. G.722 is sampled at 16000 Hz but its RTP clock runs at 8000
+// Hz, RFC 3551 section 4.5.2, and Opus is always `opus/48000/2` in the
+// rtpmap. The channels a sender will send are in `a=fmtp` `sprop-stereo`;
+// `stereo` is what the receiver prefers, RFC 7587 section 7.1.
+
+use std::collections::VecDeque;
+
+use rtp;
+use sdp::Media;
+use random::random_u64;
+use media::{ AudioFrame, Depacketizer as DepacketizerTrait, Sequence };
+use error::{ Error, Result };
+
+/// Payload bytes to put in a packet unless told otherwise.
+pub const DEFAULT_MAX_PAYLOAD: usize = 1200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// G.711 µ-law
+    Pcmu,
+    /// G.711 A-law
+    Pcma,
+    G722,
+    /// 16 bit signed big endian linear PCM
+    L16,
+    Opus
+}
+
+/// The encoding of a stream with its RTP clock rate and channel count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub encoding  : Encoding,
+    pub clock_rate: u32,
+    pub channels  : u8
+}
+
+/// Passes through the frames of the simple audio payload formats, one
+/// per packet.
+#[derive(Debug, Clone)]
+pub struct Depacketizer {
+    format  : Format,
+    sequence: Sequence,
+    ready   : VecDeque<AudioFrame>
+}
+
+/// Splits audio into RTP packets, advancing the sequence number and the
+/// timestamp.
+#[derive(Debug, Clone)]
+pub struct Packetizer {
+    format     : Format,
+    builder    : rtp::PacketBuilder,
+    max_payload: usize,
+    started    : bool
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_ascii_uppercase().as_ref() {
+            "PCMU" => Some(Encoding::Pcmu),
+            "PCMA" => Some(Encoding::Pcma),
+            "G722" => Some(Encoding::G722),
+            "L16" => Some(Encoding::L16),
+            "OPUS" => Some(Encoding::Opus),
+            _ => None
+        }
+    }
+}
+
+impl AsRef<str> for Encoding {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Encoding::Pcmu => "PCMU",
+            Encoding::Pcma => "PCMA",
+            Encoding::G722 => "G722",
+            Encoding::L16 => "L16",
+            Encoding::Opus => "opus"
+        }
+    }
+}
+
+impl Format {
+    pub fn new (encoding: Encoding, clock_rate: u32, channels: u8) -> Format {
+        Format { encoding, clock_rate, channels }
+    }
+    /// The format of a static payload type of RFC 3551.
+    pub fn from_payload_type(payload_type: u8) -> Option<Format> {
+        match payload_type {
+            0 => Some(Format::new(Encoding::Pcmu, 8000, 1)),
+            8 => Some(Format::new(Encoding::Pcma, 8000, 1)),
+            9 => Some(Format::new(Encoding::G722, 8000, 1)),
+            10 => Some(Format::new(Encoding::L16, 44100, 2)),
+            11 => Some(Format::new(Encoding::L16, 44100, 1)),
+            _ => None
+        }
+    }
+    /// The format of `payload_type` in a media description, from its
+    /// `a=rtpmap` or else the static payload types. The description is
+    /// taken to be the sender's, as from DESCRIBE.
+    pub fn from_media(media: &Media, payload_type: u8) -> Result<Format> {
+        let rtpmap = match media.rtpmap(payload_type) {
+            Some(rtpmap) => rtpmap,
+            None => return Format::from_payload_type(payload_type).ok_or_else(|| invalid("Payload type has no rtpmap"))
+        };
+        let encoding = Encoding::from_name(&rtpmap.encoding).ok_or_else(|| invalid("Unsupported audio encoding"))?;
+        let channels = match encoding {
+            Encoding::Opus => {
+                let stereo = media.fmtp(&payload_type.to_string())
+                                  .and_then(|fmtp| fmtp.get("sprop-stereo").map(|v| v.trim() == "1"))
+                                  .unwrap_or(false);
+                if stereo { 2 } else { 1 }
+            },
+            _ => rtpmap.params.unwrap_or(1) as u8
+        };
+        let clock_rate = match encoding {
+            Encoding::G722 => 8000,
+            Encoding::Opus => 48000,
+            _ => rtpmap.clock_rate
+        };
+        if clock_rate == 0 || channels == 0 {
+            return Err(invalid("Invalid audio rtpmap"))
+        }
+        Ok(Format::new(encoding, clock_rate, channels))
+    }
+    /// The bytes of one sample of every channel, 0 for Opus.
+    pub fn sample_size(&self) -> usize {
+        let channels = self.channels.max(1) as usize;
+        match self.encoding {
+            Encoding::Pcmu | Encoding::Pcma | Encoding::G722 => channels,
+            Encoding::L16 => 2 * channels,
+            Encoding::Opus => 0
+        }
+    }
+    /// The RTP timestamps a payload lasts.
+    pub fn duration(&self, payload: &[u8]) -> Result<u32> {
+        match self.encoding {
+            Encoding::Opus => opus_duration(payload),
+            _ => Ok((payload.len() / self.sample_size()) as u32)
+        }
+    }
+}
+
+impl Depacketizer {
+    pub fn new (format: Format) -> Depacketizer {
+        Depacketizer { format, sequence: Sequence::new(), ready: VecDeque::new() }
+    }
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+}
+
+impl DepacketizerTrait for Depacketizer {
+    type Frame = AudioFrame;
+
+    fn push(&mut self, packet: &rtp::Packet) -> Result<()> {
+        let loss = match self.sequence.next(packet.sequence_number()) {
+            Some(lost) => lost,
+            None => return Ok(())
+        };
+        let payload = packet.payload();
+        if payload.is_empty() {
+            return Ok(())
+        }
+        if self.format.encoding == Encoding::Opus {
+            opus_duration(payload)?;
+        } else if !payload.len().is_multiple_of(self.format.sample_size()) {
+            return Err(invalid("Audio payload holds a partial sample"))
+        }
+        self.ready.push_back(AudioFrame { timestamp: packet.timestamp(), data: payload.to_vec(), loss });
+        Ok(())
+    }
+    fn pull(&mut self) -> Option<AudioFrame> {
+        self.ready.pop_front()
+    }
+}
+
+impl Packetizer {
+    /// Starts at a random sequence number and timestamp.
+    pub fn new (format: Format, payload_type: u8, ssrc: u32) -> Packetizer {
+        let random = random_u64();
+        Packetizer {
+            format,
+            builder: rtp::PacketBuilder::new(payload_type, random as u16, (random >> 32) as u32, ssrc),
+            max_payload: DEFAULT_MAX_PAYLOAD,
+            started: false
+        }
+    }
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+    pub fn set_max_payload(&mut self, max_payload: usize) {
+        self.max_payload = max_payload;
+    }
+    /// The sequence number of the next packet, for `RTP-Info`.
+    pub fn sequence_number(&self) -> u16 {
+        self.builder.sequence_number
+    }
+    /// The timestamp of the next packet, for `RTP-Info`.
+    pub fn timestamp(&self) -> u32 {
+        self.builder.timestamp
+    }
+    /// Packetizes `data`, whole samples or one Opus packet. The first
+    /// packet of the stream has the marker bit, as it starts a talkspurt.
+    pub fn packetize(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let chunk = match self.format.encoding {
+            Encoding::Opus => {
+                if data.len() > self.max_payload {
+                    return Err(invalid("Opus packet is larger than the maximum payload"))
+                }
+                data.len().max(1)
+            },
+            _ => {
+                let sample_size = self.format.sample_size();
+                if !data.len().is_multiple_of(sample_size) {
+                    return Err(invalid("Audio data holds a partial sample"))
+                }
+                (self.max_payload / sample_size).max(1) * sample_size
+            }
+        };
+        let mut packets = Vec::new();
+        for payload in data.chunks(chunk) {
+            let duration = self.format.duration(payload)?;
+            self.builder.marker = !self.started;
+            self.started = true;
+            packets.push(self.builder.build(payload)?);
+            self.builder.sequence_number = self.builder.sequence_number.wrapping_add(1);
+            self.builder.timestamp = self.builder.timestamp.wrapping_add(duration);
+        }
+        Ok(packets)
+    }
+}
+
+/// The duration of an Opus packet at 48000 Hz from its TOC byte.
+/// https://tools.ietf.org/html/rfc6716#section-3.1
+pub fn opus_duration(packet: &[u8]) -> Result<u32> {
+    let toc = *packet.first().ok_or_else(|| invalid("Empty Opus packet"))?;
+    let config = toc >> 3;
+    let frame = match config {
+        0..=11 => [480, 960, 1920, 2880][config as usize % 4],
+        12..=15 => [480, 960][config as usize % 2],
+        _ => [120, 240, 480, 960][config as usize % 4]
+    };
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => u32::from(*packet.get(1).ok_or_else(|| invalid("Opus packet is missing its frame count"))? & 0x3f)
+    };
+    if frames == 0 || frames * frame > 5760 {
+        return Err(invalid("Opus packet lasts longer than 120 ms"))
+    }
+    Ok(frames * frame)
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Media(reason.to_string())
+}
+
+
+
+#[test]
+fn test() {
+    let mut media = Media::new("audio", 0, "RTP/AVP", vec!["0".to_string(), "9".to_string(), "96".to_string(), "97".to_string()]);
+    media.add_attribute("rtpmap", Some("9 G722/8000"));
+    media.add_attribute("rtpmap", Some("96 L16/16000/2"));
+    media.add_attribute("rtpmap", Some("97 opus/48000/2"));
+    media.add_attribute("fmtp", Some("97 sprop-stereo=1; stereo=0; useinbandfec=1"));
+    assert_eq!(Format::from_media(&media, 0).unwrap(), Format::new(Encoding::Pcmu, 8000, 1));
+    assert_eq!(Format::from_media(&media, 9).unwrap(), Format::new(Encoding::G722, 8000, 1));
+    assert_eq!(Format::from_media(&media, 96).unwrap(), Format::new(Encoding::L16, 16000, 2));
+    assert_eq!(Format::from_media(&media, 97).unwrap(), Format::new(Encoding::Opus, 48000, 2));
+    assert!(Format::from_media(&media, 98).is_err());
+
+    // 50 ms of PCMU in packets of at most 20 ms.
+    let mut packetizer = Packetizer::new(Format::from_payload_type(0).unwrap(), 0, 7);
+    packetizer.set_max_payload(160);
+    let (seq, timestamp) = (packetizer.sequence_number(), packetizer.timestamp());
+    let packets = packetizer.packetize(&[0xff; 400]).unwrap();
+    assert_eq!(packets.len(), 3);
+    assert_eq!(packetizer.timestamp(), timestamp.wrapping_add(400));
+
+    let mut depacketizer = Depacketizer::new(*packetizer.format());
+    for (i, buf) in packets.iter().enumerate() {
+        let packet = rtp::Packet::parse(buf).unwrap();
+        assert_eq!(packet.marker(), i == 0);
+        assert_eq!(packet.sequence_number(), seq.wrapping_add(i as u16));
+        if i != 1 {
+            depacketizer.push(&packet).unwrap();
+        }
+    }
+    assert_eq!(depacketizer.pull().unwrap().timestamp, timestamp);
+    let last = depacketizer.pull().unwrap();
+    assert_eq!((last.timestamp, last.data.len(), last.loss), (timestamp.wrapping_add(320), 80, 1));
+
+    // A 20 ms CELT frame, and two 60 ms SILK frames.
+    assert_eq!(opus_duration(&[0xf8, 0]).unwrap(), 960);
+    assert_eq!(opus_duration(&[0x19, 0]).unwrap(), 5760);
+    let mut packetizer = Packetizer::new(Format::new(Encoding::Opus, 48000, 2), 97, 7);
+    let timestamp = packetizer.timestamp();
+    packetizer.packetize(&[0xf8, 1, 2, 3]).unwrap();
+    assert_eq!(packetizer.timestamp(), timestamp.wrapping_add(960));
+}
@@ -0,0 +1,644 @@
+// Authentication
+// https://tools.ietf.org/html/rfc2326#appendix-D.2
+//
+// RTSP borrows HTTP authentication:
+//   Basic   https://tools.ietf.org/html/rfc7617
+//   Digest  https://tools.ietf.org/html/rfc7616
+//
+// For example:
+//   WWW-Authenticate: Digest realm="IP Camera", nonce="a1b2c3", qop="auth", algorithm=MD5
+//   Authorization: Digest username="admin", realm="IP Camera", nonce="a1b2c3",
+//                  uri="rtsp://10.0.0.2/live", response="...", algorithm=MD5,
+//                  qop=auth, nc=00000001, cnonce="d4e5f6"
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+use method::Method;
+use header::Headers;
+use request::Request;
+use response::Response;
+use status::StatusCode;
+use url::Url;
+use digest;
+use random::random_u64;
+use error::{ Error, Result };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestChallenge {
+    pub realm    : String,
+    pub nonce    : String,
+    pub opaque   : Option<String>,
+    pub algorithm: Algorithm,
+    /// Empty for RFC 2069 style digests without `qop`.
+    pub qop      : Vec<String>,
+    /// The nonce expired but the credentials were right.
+    pub stale    : bool
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Challenge {
+    Basic { realm: String },
+    Digest(DigestChallenge)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String
+}
+
+/// Looks up credentials for a URL once the server asks for them.
+pub trait CredentialProvider {
+    fn credentials(&self, url: &Url, realm: &str) -> Option<Credentials>;
+}
+
+impl<F: Fn(&Url, &str) -> Option<Credentials>> CredentialProvider for F {
+    fn credentials(&self, url: &Url, realm: &str) -> Option<Credentials> {
+        self(url, realm)
+    }
+}
+
+impl fmt::Debug for dyn CredentialProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CredentialProvider")
+    }
+}
+
+/// The server's user database.
+pub trait CredentialStore: Send + Sync {
+    /// The password of `username`, for stores that keep them.
+    fn password(&self, _username: &str) -> Option<String> {
+        None
+    }
+    /// `H(username:realm:password)` for `username`, see `ha1`. Stores that
+    /// keep hashes rather than passwords implement this one.
+    fn ha1(&self, username: &str, realm: &str, algorithm: Algorithm) -> Option<String> {
+        self.password(username).map(|password| ha1(algorithm, username, realm, &password))
+    }
+}
+
+impl fmt::Debug for dyn CredentialStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CredentialStore")
+    }
+}
+
+/// Checks `Authorization` headers on the server side and issues challenges.
+///
+/// Nonces carry the time they were issued and a keyed hash of it, so no
+/// state needs to be kept for them: an expired nonce with otherwise right
+/// credentials gets a challenge with `stale=true`. Nonce counts are not
+/// tracked, replayed requests within the nonce lifetime are accepted.
+/// The key is read from /dev/urandom; without one it is only as
+/// unpredictable as a randomly keyed hash of the clock.
+#[derive(Debug)]
+pub struct Verifier {
+    realm    : String,
+    store    : Box<dyn CredentialStore>,
+    algorithm: Algorithm,
+    basic    : bool,
+    lifetime : Duration,
+    secret   : String
+}
+
+/// Answers a challenge, counting the requests made with its nonce.
+#[derive(Debug, Clone)]
+pub struct Authenticator {
+    credentials: Credentials,
+    challenge  : Challenge,
+    nc         : u32
+}
+
+impl Algorithm {
+    pub fn is_sess(&self) -> bool {
+        matches!(*self, Algorithm::Md5Sess | Algorithm::Sha256Sess)
+    }
+    /// The hex digest of `data`.
+    pub fn hash(&self, data: &str) -> String {
+        match *self {
+            Algorithm::Md5 | Algorithm::Md5Sess => digest::hex(&digest::md5(data.as_bytes())),
+            Algorithm::Sha256 | Algorithm::Sha256Sess => digest::hex(&digest::sha256(data.as_bytes()))
+        }
+    }
+}
+
+impl AsRef<str> for Algorithm {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Algorithm::Md5        => "MD5",
+            Algorithm::Md5Sess    => "MD5-sess",
+            Algorithm::Sha256     => "SHA-256",
+            Algorithm::Sha256Sess => "SHA-256-sess"
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Algorithm> {
+        match s.to_ascii_uppercase().as_ref() {
+            "MD5"          => Ok(Algorithm::Md5),
+            "MD5-SESS"     => Ok(Algorithm::Md5Sess),
+            "SHA-256"      => Ok(Algorithm::Sha256),
+            "SHA-256-SESS" => Ok(Algorithm::Sha256Sess),
+            _ => Err(Error::Header)
+        }
+    }
+}
+
+impl DigestChallenge {
+    pub fn new (realm: &str, nonce: &str, algorithm: Algorithm) -> DigestChallenge {
+        DigestChallenge {
+            realm: realm.to_string(),
+            nonce: nonce.to_string(),
+            opaque: None,
+            algorithm,
+            qop: vec!["auth".to_string()],
+            stale: false
+        }
+    }
+}
+
+impl Challenge {
+    pub fn realm(&self) -> &str {
+        match *self {
+            Challenge::Basic { ref realm } => realm,
+            Challenge::Digest(ref digest) => &digest.realm
+        }
+    }
+    /// The strongest challenge among the `WWW-Authenticate` headers that
+    /// this crate can answer.
+    pub fn best(headers: &Headers) -> Option<Challenge> {
+        headers.get_all("WWW-Authenticate").into_iter()
+               .filter_map(|value| value.parse::<Challenge>().ok())
+               .max_by_key(|challenge| match *challenge {
+                   Challenge::Basic { .. } => 0,
+                   Challenge::Digest(ref digest) if digest.algorithm == Algorithm::Sha256
+                                                 || digest.algorithm == Algorithm::Sha256Sess => 2,
+                   Challenge::Digest(_) => 1
+               })
+    }
+}
+
+impl FromStr for Challenge {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Challenge> {
+        let s = s.trim();
+        let (scheme, rest) = match s.find(' ') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, "")
+        };
+        let params = params(rest);
+        let param = |name: &str| params.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone());
+        let realm = param("realm").unwrap_or_default();
+        if scheme.eq_ignore_ascii_case("Basic") {
+            return Ok(Challenge::Basic { realm })
+        }
+        if !scheme.eq_ignore_ascii_case("Digest") {
+            return Err(Error::Header)
+        }
+        Ok(Challenge::Digest(DigestChallenge {
+            realm,
+            nonce: param("nonce").ok_or(Error::Header)?,
+            opaque: param("opaque"),
+            algorithm: match param("algorithm") {
+                Some(algorithm) => algorithm.parse()?,
+                None => Algorithm::Md5
+            },
+            qop: param("qop").map(|qop| qop.split(',').map(|q| q.trim().to_string())
+                                             .filter(|q| !q.is_empty()).collect())
+                             .unwrap_or_default(),
+            stale: param("stale").map(|stale| stale.eq_ignore_ascii_case("true")).unwrap_or(false)
+        }))
+    }
+}
+
+impl fmt::Display for Challenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Challenge::Basic { ref realm } => write!(f, "Basic realm=\"{}\"", realm),
+            Challenge::Digest(ref digest) => {
+                write!(f, "Digest realm=\"{}\", nonce=\"{}\"", digest.realm, digest.nonce)?;
+                if let Some(ref opaque) = digest.opaque {
+                    write!(f, ", opaque=\"{}\"", opaque)?;
+                }
+                write!(f, ", algorithm={}", digest.algorithm)?;
+                if !digest.qop.is_empty() {
+                    write!(f, ", qop=\"{}\"", digest.qop.join(","))?;
+                }
+                if digest.stale {
+                    f.write_str(", stale=true")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Credentials {
+    pub fn new (username: &str, password: &str) -> Credentials {
+        Credentials { username: username.to_string(), password: password.to_string() }
+    }
+    /// The credentials in the userinfo of `url`, if any.
+    pub fn from_url(url: &Url) -> Option<Credentials> {
+        url.username().map(|username| Credentials::new(username, url.password().unwrap_or("")))
+    }
+}
+
+impl Authenticator {
+    pub fn new (credentials: Credentials, challenge: Challenge) -> Authenticator {
+        Authenticator { credentials, challenge, nc: 0 }
+    }
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+    /// The `Authorization` header value for a `method` request to `uri`.
+    pub fn authorization(&mut self, method: &Method, uri: &str) -> String {
+        let digest = match self.challenge {
+            Challenge::Basic { .. } => {
+                let token = format!("{}:{}", self.credentials.username, self.credentials.password);
+                return format!("Basic {}", digest::base64_encode(token.as_bytes()))
+            },
+            Challenge::Digest(ref digest) => digest
+        };
+        self.nc = self.nc.wrapping_add(1);
+        let nc = format!("{:08x}", self.nc);
+        let cnonce = random_hex();
+        // Only `auth` is supported, `auth-int` would need the body hashed.
+        let qop = if digest.qop.iter().any(|q| q.eq_ignore_ascii_case("auth")) { Some("auth") } else { None };
+        let ha1 = ha1(digest.algorithm, &self.credentials.username, &digest.realm, &self.credentials.password);
+        let response = response(digest.algorithm, &ha1, &digest.nonce, &nc, &cnonce, qop, method, uri);
+
+        let mut value = format!("Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", response=\"{}\", algorithm={}",
+                                quote(&self.credentials.username), quote(&digest.realm), digest.nonce, uri, response,
+                                digest.algorithm);
+        if let Some(ref opaque) = digest.opaque {
+            value.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        if let Some(qop) = qop {
+            value.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+        }
+        value
+    }
+}
+
+impl Verifier {
+    /// Digest with MD5 only, nonces valid for a minute.
+    pub fn new<S: CredentialStore + 'static>(realm: &str, store: S) -> Verifier {
+        Verifier {
+            realm: realm.to_string(),
+            store: Box::new(store),
+            algorithm: Algorithm::Md5,
+            basic: false,
+            lifetime: Duration::from_secs(60),
+            secret: format!("{}{}", random_hex(), random_hex())
+        }
+    }
+    pub fn realm(&self) -> &str {
+        &self.realm
+    }
+    /// The algorithm challenges ask for, and Basic passwords are hashed with
+    /// for `CredentialStore::ha1`.
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+    }
+    /// Also offers and accepts Basic, which sends the password in the clear.
+    pub fn set_basic(&mut self, basic: bool) {
+        self.basic = basic;
+    }
+    pub fn set_nonce_lifetime(&mut self, lifetime: Duration) {
+        self.lifetime = lifetime;
+    }
+    /// The challenges to send, strongest first.
+    pub fn challenges(&self, stale: bool) -> Vec<Challenge> {
+        let mut digest = DigestChallenge::new(&self.realm, &self.nonce(unix_time()), self.algorithm);
+        digest.stale = stale;
+        let mut challenges = vec![Challenge::Digest(digest)];
+        if self.basic {
+            challenges.push(Challenge::Basic { realm: self.realm.clone() });
+        }
+        challenges
+    }
+    /// The user `request` is authorized as, or the `401 Unauthorized`
+    /// answer to it.
+    pub fn check(&self, request: &Request) -> ::std::result::Result<String, Response> {
+        let mut stale = false;
+        if let Some(authorization) = request.headers().get("Authorization") {
+            match self.verify(request, authorization) {
+                Verdict::Valid(username) => return Ok(username),
+                Verdict::Stale => stale = true,
+                Verdict::Invalid => ()
+            }
+        }
+        let mut response = Response::with_status(StatusCode::Unauthorized);
+        if let Some(cseq) = request.cseq() {
+            response.set_cseq(cseq);
+        }
+        for challenge in self.challenges(stale) {
+            response.headers_mut().append("WWW-Authenticate", challenge.to_string());
+        }
+        Err(response)
+    }
+    fn verify(&self, request: &Request, authorization: &str) -> Verdict {
+        let authorization = authorization.trim();
+        let (scheme, rest) = match authorization.find(' ') {
+            Some(i) => (&authorization[..i], authorization[i + 1..].trim()),
+            None => return Verdict::Invalid
+        };
+        if scheme.eq_ignore_ascii_case("Basic") && self.basic {
+            let decoded = digest::base64_decode(rest).and_then(|bytes| String::from_utf8(bytes).ok());
+            let (username, password) = match decoded.as_ref().and_then(|d| d.find(':').map(|i| (&d[..i], &d[i + 1..]))) {
+                Some(pair) => pair,
+                None => return Verdict::Invalid
+            };
+            return match self.store.ha1(username, &self.realm, self.algorithm) {
+                Some(ref stored) if equal(stored, &ha1(self.algorithm, username, &self.realm, password)) =>
+                    Verdict::Valid(username.to_string()),
+                _ => Verdict::Invalid
+            }
+        }
+        if !scheme.eq_ignore_ascii_case("Digest") {
+            return Verdict::Invalid
+        }
+        let params = params(rest);
+        let param = |name: &str| params.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+        let (username, nonce, uri, given) = match (param("username"), param("nonce"), param("uri"), param("response")) {
+            (Some(username), Some(nonce), Some(uri), Some(given)) => (username, nonce, uri, given),
+            _ => return Verdict::Invalid
+        };
+        let algorithm = match param("algorithm") {
+            Some(algorithm) => match algorithm.parse::<Algorithm>() {
+                Ok(algorithm) => algorithm,
+                Err(_) => return Verdict::Invalid
+            },
+            None => Algorithm::Md5
+        };
+        if param("realm") != Some(self.realm.as_str()) || algorithm != self.algorithm || uri != request.uri() {
+            return Verdict::Invalid
+        }
+        let issued = match self.issued(nonce) {
+            Some(issued) => issued,
+            None => return Verdict::Invalid
+        };
+        let qop = param("qop");
+        if qop.is_some() && qop != Some("auth") {
+            return Verdict::Invalid
+        }
+        let stored = match self.store.ha1(username, &self.realm, algorithm) {
+            Some(stored) => stored,
+            None => return Verdict::Invalid
+        };
+        let expected = response(algorithm, &stored, nonce, param("nc").unwrap_or(""), param("cnonce").unwrap_or(""),
+                                qop, request.method(), uri);
+        if !equal(&expected, given) {
+            return Verdict::Invalid
+        }
+        if unix_time().saturating_sub(issued) > self.lifetime.as_secs() {
+            return Verdict::Stale
+        }
+        Verdict::Valid(username.to_string())
+    }
+    /// The issue time in hex, followed by a keyed hash of it.
+    fn nonce(&self, issued: u64) -> String {
+        let mac = digest::hex(&digest::sha256(format!("{:016x}:{}", issued, self.secret).as_bytes()));
+        format!("{:016x}{}", issued, &mac[..32])
+    }
+    /// When `nonce` was issued, `None` if it is not one of ours.
+    fn issued(&self, nonce: &str) -> Option<u64> {
+        if nonce.len() != 48 || !nonce.is_char_boundary(16) {
+            return None
+        }
+        let issued = u64::from_str_radix(&nonce[..16], 16).ok()?;
+        if equal(&self.nonce(issued), nonce) { Some(issued) } else { None }
+    }
+}
+
+enum Verdict {
+    Valid(String),
+    Stale,
+    Invalid
+}
+
+/// Compares without returning early, so that timing does not tell how
+/// much of a secret was right.
+fn equal(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// `H(username:realm:password)`, what a server may store instead of the
+/// password.
+pub fn ha1(algorithm: Algorithm, username: &str, realm: &str, password: &str) -> String {
+    algorithm.hash(&format!("{}:{}:{}", username, realm, password))
+}
+
+/// The digest `response` for a stored `ha1`, as RFC 7616 section 3.4.1
+/// defines it. `nc` and `cnonce` are only used with `qop` or a `-sess`
+/// algorithm.
+#[allow(clippy::too_many_arguments)]
+pub fn response(algorithm: Algorithm, ha1: &str, nonce: &str, nc: &str, cnonce: &str,
+                qop: Option<&str>, method: &Method, uri: &str) -> String {
+    let ha1 = if algorithm.is_sess() {
+        algorithm.hash(&format!("{}:{}:{}", ha1, nonce, cnonce))
+    } else {
+        ha1.to_string()
+    };
+    let ha2 = algorithm.hash(&format!("{}:{}", method, uri));
+    match qop {
+        Some(qop) => algorithm.hash(&format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, qop, ha2)),
+        None => algorithm.hash(&format!("{}:{}:{}", ha1, nonce, ha2))
+    }
+}
+
+/// Splits `name=value, name="quoted, value"` pairs, unquoting the values.
+pub(crate) fn params(s: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        while let Some(&c) = chars.peek() {
+            if c == ',' || c.is_whitespace() { chars.next(); } else { break }
+        }
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c == ',' { break }
+            name.push(c);
+            chars.next();
+        }
+        if name.is_empty() && chars.peek().is_none() {
+            return params
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek() == Some(&' ') {
+                chars.next();
+            }
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c)
+                    }
+                }
+            }
+            while let Some(&c) = chars.peek() {
+                if c == ',' { break }
+                value.push(c);
+                chars.next();
+            }
+        }
+        params.push((name.trim().to_string(), value.trim().to_string()));
+    }
+}
+
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 64 random bits in hex, for nonces.
+fn random_hex() -> String {
+    format!("{:016x}", random_u64())
+}
+
+
+
+#[test]
+fn test() {
+    let challenge: Challenge = "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+                                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+                                opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"".parse().unwrap();
+    match challenge {
+        Challenge::Digest(ref digest) => {
+            assert_eq!(digest.realm, "testrealm@host.com");
+            assert_eq!(digest.qop, vec!["auth", "auth-int"]);
+            assert_eq!(digest.algorithm, Algorithm::Md5);
+            assert!(!digest.stale);
+        },
+        ref other => panic!("{:?}", other)
+    }
+    assert_eq!(challenge.to_string().parse::<Challenge>().unwrap(), challenge);
+
+    // https://tools.ietf.org/html/rfc2617#section-3.5
+    let ha1 = ha1(Algorithm::Md5, "Mufasa", "testrealm@host.com", "Circle Of Life");
+    let method = Method::Extension("GET".to_string());
+    assert_eq!(response(Algorithm::Md5, &ha1, "dcd98b7102dd2f0e8b11d0f600bfb0c093", "00000001", "0a4f113b",
+                        Some("auth"), &method, "/dir/index.html"),
+               "6629fae49393a05397450978507c4ef1");
+    // https://tools.ietf.org/html/rfc7616#section-3.9.1
+    let ha1 = self::ha1(Algorithm::Sha256, "Mufasa", "http-auth@example.org", "Circle of Life");
+    assert_eq!(response(Algorithm::Sha256, &ha1, "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", "00000001",
+                        "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ", Some("auth"), &method, "/dir/index.html"),
+               "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1");
+
+    let mut headers = Headers::new();
+    headers.append("WWW-Authenticate", "Basic realm=\"cam\"");
+    headers.append("WWW-Authenticate", "Digest realm=\"cam\", nonce=\"n1\", algorithm=SHA-256, qop=\"auth\", stale=TRUE");
+    headers.append("WWW-Authenticate", "Digest realm=\"cam\", nonce=\"n2\"");
+    headers.append("WWW-Authenticate", "Bearer realm=\"cam\"");
+    let best = Challenge::best(&headers).unwrap();
+    match best {
+        Challenge::Digest(ref digest) => {
+            assert_eq!(digest.nonce, "n1");
+            assert!(digest.stale);
+        },
+        ref other => panic!("{:?}", other)
+    }
+
+    let mut authenticator = Authenticator::new(Credentials::new("admin", "12345"), best);
+    let first = authenticator.authorization(&Method::Describe, "rtsp://cam/live");
+    let second = authenticator.authorization(&Method::Describe, "rtsp://cam/live");
+    let params = params(&first["Digest ".len()..]);
+    let param = |name: &str| params.iter().find(|(k, _)| k == name).unwrap().1.clone();
+    assert_eq!(param("username"), "admin");
+    assert_eq!(param("uri"), "rtsp://cam/live");
+    assert_eq!(param("nc"), "00000001");
+    assert!(second.contains("nc=00000002"));
+    let ha1 = self::ha1(Algorithm::Sha256, "admin", "cam", "12345");
+    assert_eq!(param("response"), response(Algorithm::Sha256, &ha1, "n1", "00000001", &param("cnonce"),
+                                           Some("auth"), &Method::Describe, "rtsp://cam/live"));
+
+    let mut basic = Authenticator::new(Credentials::new("Aladdin", "open sesame"), Challenge::Basic { realm: "x".to_string() });
+    assert_eq!(basic.authorization(&Method::Options, "*"), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+}
+
+#[test]
+fn verifier() {
+    use std::collections::HashMap;
+
+    struct Users(HashMap<String, String>);
+    impl CredentialStore for Users {
+        fn password(&self, username: &str) -> Option<String> {
+            self.0.get(username).cloned()
+        }
+    }
+    let users = Users(vec![("admin".to_string(), "12345".to_string())].into_iter().collect());
+    let mut verifier = Verifier::new("cam", users);
+    verifier.set_algorithm(Algorithm::Sha256);
+    verifier.set_basic(true);
+
+    let mut request = Request::new(Method::Describe, "rtsp://cam/live");
+    request.set_cseq(7);
+    let response = verifier.check(&request).unwrap_err();
+    assert_eq!(response.status(), StatusCode::Unauthorized);
+    assert_eq!(response.cseq(), Some(7));
+    assert_eq!(response.headers().get_all("WWW-Authenticate").len(), 2);
+
+    // What the client makes of the challenge gets in.
+    let challenge = Challenge::best(response.headers()).unwrap();
+    let mut authenticator = Authenticator::new(Credentials::new("admin", "12345"), challenge.clone());
+    let authorization = authenticator.authorization(request.method(), request.uri());
+    request.headers_mut().insert("Authorization", authorization);
+    assert_eq!(verifier.check(&request).unwrap(), "admin");
+
+    let mut wrong = Authenticator::new(Credentials::new("admin", "54321"), challenge);
+    let authorization = wrong.authorization(request.method(), request.uri());
+    request.headers_mut().insert("Authorization", authorization);
+    assert!(verifier.check(&request).is_err());
+
+    // Right credentials with an old nonce are stale.
+    let mut old = DigestChallenge::new("cam", &verifier.nonce(unix_time() - 3600), Algorithm::Sha256);
+    old.qop.clear();
+    let mut authenticator = Authenticator::new(Credentials::new("admin", "12345"), Challenge::Digest(old));
+    let authorization = authenticator.authorization(request.method(), request.uri());
+    request.headers_mut().insert("Authorization", authorization);
+    match Challenge::best(verifier.check(&request).unwrap_err().headers()) {
+        Some(Challenge::Digest(ref digest)) => assert!(digest.stale),
+        other => panic!("{:?}", other)
+    }
+    // A nonce the server never issued is not.
+    let authorization = authenticator.authorization(request.method(), request.uri());
+    request.headers_mut().insert("Authorization", authorization.replace("nonce=\"0", "nonce=\"1"));
+    match Challenge::best(verifier.check(&request).unwrap_err().headers()) {
+        Some(Challenge::Digest(ref digest)) => assert!(!digest.stale),
+        other => panic!("{:?}", other)
+    }
+
+    request.headers_mut().insert("Authorization", "Basic YWRtaW46MTIzNDU=");
+    assert_eq!(verifier.check(&request).unwrap(), "admin");
+    request.headers_mut().insert("Authorization", "Basic YWRtaW46MTIzNDY=");
+    assert!(verifier.check(&request).is_err());
+}
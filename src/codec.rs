@@ -0,0 +1,283 @@
+// A push based decoder that splits a byte stream into RTSP messages, and
+// the matching encoder.
+//
+// The decoder never touches a socket: bytes are handed to `push` as they
+// arrive, however they are chunked, and complete messages are taken out
+// with `decode`. This keeps the framing usable from blocking sockets, mio
+// and async runtimes alike.
+
+use std::fmt;
+use std::io::{ self, Read, Write };
+
+use request::Request;
+use response::Response;
+use header::Headers;
+use interleaved::Frame;
+use error::{ Error, Result };
+use parse;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Request(Request),
+    Response(Response),
+    /// Binary data interleaved on the connection, usually RTP and RTCP.
+    Data(Frame)
+}
+
+impl Message {
+    /// Appends the wire form of the message to `buf`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        match *self {
+            Message::Request(ref request) => request.encode(buf),
+            Message::Response(ref response) => response.encode(buf),
+            Message::Data(ref frame) => frame.encode(buf)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buf    : Vec<u8>,
+    // Body bytes of a rejected message that have not arrived yet.
+    discard: usize,
+    broken : bool
+}
+
+impl Decoder {
+    pub fn new () -> Decoder {
+        Decoder { buf: Vec::new(), discard: 0, broken: false }
+    }
+    /// Appends bytes received from the peer.
+    pub fn push(&mut self, data: &[u8]) {
+        let skip = self.discard.min(data.len());
+        self.discard -= skip;
+        if !self.broken {
+            self.buf.extend_from_slice(&data[skip..]);
+        }
+    }
+    /// Whether the stream can no longer be framed, after a message with a
+    /// `Content-Length` that is not a number or a head that grew too large
+    /// without ending. The connection should be closed; `decode` fails from
+    /// then on.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+    /// Reads once from `reader` into the decoder, returning the number of
+    /// bytes read. `0` means the peer closed the connection.
+    pub fn read_from<R: Read>(&mut self, reader: &mut R) -> Result<usize> {
+        let mut chunk = [0u8; 4096];
+        let n = reader.read(&mut chunk)?;
+        self.push(&chunk[..n]);
+        Ok(n)
+    }
+    /// The number of bytes buffered but not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+    /// Takes the next complete message out of the buffer.
+    ///
+    /// `Ok(None)` means more bytes are needed. When a malformed message is
+    /// met its head and the body its `Content-Length` announces are dropped,
+    /// so that decoding can resume with whatever follows it, and the error
+    /// is returned. Without a usable `Content-Length`, or without the end of
+    /// an oversized head, the decoder is broken, see `is_broken`.
+    pub fn decode(&mut self) -> Result<Option<Message>> {
+        if self.broken {
+            return Err(Error::Header)
+        }
+        let skip = parse::skip_empty_lines(&self.buf);
+        self.buf.drain(..skip);
+        if self.buf.is_empty() {
+            return Ok(None)
+        }
+
+        let result = if self.buf[0] == b'$' {
+            Frame::parse(&self.buf).map(|(frame, len)| (Message::Data(frame), len))
+        } else if self.buf.len() < 5 && b"RTSP/".starts_with(&self.buf) {
+            Err(Error::Incomplete)
+        } else if self.buf.starts_with(b"RTSP/") {
+            Response::parse(&self.buf).map(|(response, len)| (Message::Response(response), len))
+        } else {
+            Request::parse(&self.buf).map(|(request, len)| (Message::Request(request), len))
+        };
+
+        match result {
+            Ok((message, len)) => {
+                self.buf.drain(..len);
+                Ok(Some(message))
+            },
+            Err(Error::Incomplete) => Ok(None),
+            Err(e) => {
+                match parse::head_len(&self.buf).map(|len| (len, parse::body_len(&self.buf[..len]))) {
+                    Some((len, Some(body))) => {
+                        let end = len.saturating_add(body);
+                        let drained = end.min(self.buf.len());
+                        self.buf.drain(..drained);
+                        self.discard = end - drained;
+                    },
+                    // Either the length of the body or the end of the head is
+                    // unknown, so there is no telling where the next message
+                    // starts.
+                    Some((_, None)) | None => {
+                        self.buf.clear();
+                        self.broken = true;
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+
+/// Writes requests with consecutive `CSeq` numbers.
+#[derive(Debug)]
+pub struct Encoder {
+    cseq: u32
+}
+
+impl Encoder {
+    pub fn new () -> Encoder {
+        Encoder { cseq: 1 }
+    }
+    /// The `CSeq` the next request without one will be given.
+    pub fn next_cseq(&self) -> u32 {
+        self.cseq
+    }
+    /// Appends `request` to `buf`, numbering it with the next `CSeq` unless it
+    /// already carries one. Returns the `CSeq` the request went out with.
+    pub fn encode_request(&mut self, request: &Request, buf: &mut Vec<u8>) -> u32 {
+        let cseq = self.take_cseq(request);
+        write_message(buf, format_args!("{} {} {}", request.method(), request.uri(), request.version()),
+                      Some(cseq), request.headers(), request.body())
+            .expect("writing to a Vec cannot fail");
+        cseq
+    }
+    /// Like `encode_request`, writing to `w` instead.
+    pub fn write_request<W: Write>(&mut self, request: &Request, w: &mut W) -> Result<u32> {
+        let mut buf = Vec::new();
+        let cseq = self.encode_request(request, &mut buf);
+        w.write_all(&buf)?;
+        Ok(cseq)
+    }
+    fn take_cseq(&mut self, request: &Request) -> u32 {
+        let cseq = request.cseq().unwrap_or(self.cseq);
+        self.cseq = cseq.wrapping_add(1);
+        cseq
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
+}
+
+/// Writes the start line, `CSeq`, the remaining headers, a `Content-Length`
+/// matching `body` and the body itself.
+pub(crate) fn write_message<W: Write>(w: &mut W, start_line: fmt::Arguments, cseq: Option<u32>,
+                                      headers: &Headers, body: &[u8]) -> io::Result<()> {
+    write!(w, "{}\r\n", start_line)?;
+    if let Some(cseq) = cseq {
+        write!(w, "CSeq: {}\r\n", cseq)?;
+    }
+    headers.write_to(w, &["CSeq", "Content-Length"])?;
+    if !body.is_empty() {
+        write!(w, "Content-Length: {}\r\n", body.len())?;
+    }
+    w.write_all(b"\r\n")?;
+    w.write_all(body)
+}
+
+
+
+#[test]
+fn test() {
+    let stream = b"RTSP/1.0 200 OK\r\nCSeq: 1\r\nContent-Length: 3\r\n\r\nabc\
+                   $\x01\x00\x02hi\
+                   OPTIONS * RTSP/1.0\r\nCSeq: 2\r\n\r\n";
+    let mut decoder = Decoder::new();
+    let mut messages = Vec::new();
+    // Feed one byte at a time to exercise every partial state.
+    for b in stream.iter() {
+        decoder.push(&[*b]);
+        while let Some(message) = decoder.decode().unwrap() {
+            messages.push(message);
+        }
+    }
+    assert_eq!(decoder.buffered(), 0);
+    assert_eq!(messages.len(), 3);
+    match messages[0] {
+        Message::Response(ref response) => assert_eq!(response.body(), b"abc"),
+        ref other => panic!("{:?}", other)
+    }
+    match messages[1] {
+        Message::Data(ref frame) => {
+            assert_eq!(frame.channel(), 1);
+            assert_eq!(frame.payload(), b"hi");
+        },
+        ref other => panic!("{:?}", other)
+    }
+    match messages[2] {
+        Message::Request(ref request) => assert_eq!(request.cseq(), Some(2)),
+        ref other => panic!("{:?}", other)
+    }
+
+    // A broken message is reported once and decoding carries on after it.
+    decoder.push(b"PLAY rtsp://a/ RTSP/2.0\r\n\r\nPAUSE rtsp://a/ RTSP/1.0\r\n\r\n");
+    assert!(decoder.decode().is_err());
+    match decoder.decode().unwrap() {
+        Some(Message::Request(ref request)) => assert_eq!(request.method(), &::method::Method::Pause),
+        other => panic!("{:?}", other)
+    }
+
+    // The body of a rejected message is skipped, even when it is too large
+    // to buffer and arrives later.
+    decoder.push(b"ANNOUNCE rtsp://a/ RTSP/1.0\r\nCSeq: 3\r\nContent-Length: 2000000\r\n\r\n");
+    decoder.push(&[b'$'; 1000]);
+    assert!(decoder.decode().is_err());
+    decoder.push(&vec![b'$'; 1_999_000]);
+    decoder.push(b"OPTIONS * RTSP/1.0\r\nCSeq: 4\r\n\r\n");
+    match decoder.decode().unwrap() {
+        Some(Message::Request(ref request)) => assert_eq!(request.cseq(), Some(4)),
+        other => panic!("{:?}", other)
+    }
+    decoder.push(b"OPTIONS * RTSP/1.0\r\nCSeq: 5\r\nContent-Length: x\r\n\r\nOPTIONS * RTSP/1.0\r\n\r\n");
+    assert!(decoder.decode().is_err());
+    assert!(decoder.is_broken());
+    assert!(decoder.decode().is_err());
+
+    // So is a head that never ends, rather than reading the rest of it as
+    // a new message.
+    let mut decoder = Decoder::new();
+    decoder.push(b"OPTIONS * RTSP/1.0\r\nX-Padding: ");
+    decoder.push(&vec![b'a'; parse::MAX_HEAD_SIZE]);
+    assert!(decoder.decode().is_err());
+    decoder.push(b"\r\nOPTIONS * RTSP/1.0\r\n\r\n");
+    assert!(decoder.is_broken());
+    assert!(decoder.decode().is_err());
+    let mut decoder = Decoder::new();
+
+    // Every message survives encoding and decoding unchanged.
+    let mut encoder = Encoder::new();
+    let mut buf = Vec::new();
+    let mut request = Request::new(::method::Method::Setup, "rtsp://a/track1");
+    request.headers_mut().insert("Transport".to_string(), "RTP/AVP;unicast".to_string());
+    assert_eq!(encoder.encode_request(&request, &mut buf), 1);
+    assert_eq!(encoder.encode_request(&request, &mut buf), 2);
+    Message::Data(Frame::new(3, vec![0; 300]).unwrap()).encode(&mut buf);
+    decoder.push(&buf);
+    for cseq in 1..3 {
+        match decoder.decode().unwrap() {
+            Some(Message::Request(ref decoded)) => {
+                assert_eq!(decoded.cseq(), Some(cseq));
+                assert_eq!(decoded.headers().get("Transport"), request.headers().get("Transport"));
+            },
+            other => panic!("{:?}", other)
+        }
+    }
+    match decoder.decode().unwrap() {
+        Some(Message::Data(ref frame)) => assert_eq!((frame.channel(), frame.payload().len()), (3, 300)),
+        other => panic!("{:?}", other)
+    }
+}
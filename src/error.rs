@@ -5,6 +5,9 @@ use std::io::Error as IoError;
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 
+use method::Method;
+use state::State;
+
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
@@ -21,6 +24,16 @@ pub enum Error {
     Incomplete,
     Status,
     Timeout,
+    /// The method may not be sent in the session's current state
+    MethodNotValidInThisState(Method, State),
+    /// The session description could not be parsed
+    Sdp(String),
+    /// An RTP packet is malformed
+    Rtp(String),
+    /// An RTCP packet is malformed
+    Rtcp(String),
+    /// A media payload is malformed or uses an unsupported mode
+    Media(String),
     Io(IoError),
     /// Parsing a field as string failed
     Utf8(Utf8Error),
@@ -37,32 +50,34 @@ impl fmt::Debug for Void {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(self.description())
+        match *self {
+            Error::Method => f.write_str("Invalid Method specified"),
+            Error::Version => f.write_str("Invalid RTSP version specified"),
+            Error::Header => f.write_str("Invalid Header provided"),
+            Error::TooLarge => f.write_str("Message head is too large"),
+            Error::Status => f.write_str("Invalid Status provided"),
+            Error::Incomplete => f.write_str("Message is incomplete"),
+            Error::Timeout => f.write_str("Timeout"),
+            Error::MethodNotValidInThisState(ref method, state) =>
+                write!(f, "Method {} is not valid in state {}", method, state),
+            Error::Uri(ref e) => f.write_str(e),
+            Error::Sdp(ref e) => f.write_str(e),
+            Error::Rtp(ref e) => f.write_str(e),
+            Error::Rtcp(ref e) => f.write_str(e),
+            Error::Media(ref e) => f.write_str(e),
+            Error::Io(ref e) => fmt::Display::fmt(e, f),
+            Error::Utf8(ref e) => fmt::Display::fmt(e, f),
+            Error::__Nonexhaustive(ref void) =>  match *void {}
+        }
     }
 }
 
 
 impl StdError for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Method => "Invalid Method specified",
-            Error::Version => "Invalid RTSP version specified",
-            Error::Header => "Invalid Header provided",
-            Error::TooLarge => "Message head is too large",
-            Error::Status => "Invalid Status provided",
-            Error::Incomplete => "Message is incomplete",
-            Error::Timeout => "Timeout",
-            // Error::Uri(ref e) => e.description(),
-            Error::Uri(ref e) => e.as_ref(),
-            Error::Io(ref e) => e.description(),
-            Error::Utf8(ref e) => e.description(),
-            Error::__Nonexhaustive(ref void) =>  match *void {}
-        }
-    }
-    fn cause(&self) -> Option<&StdError> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self {
             Error::Io(ref error) => Some(error),
-            // Error::Uri(ref error) => Some(error),
+            Error::Utf8(ref error) => Some(error),
             _ => None,
         }
     }
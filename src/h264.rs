@@ -0,0 +1,250 @@
+// RTP Payload Format for H.264 Video
+// https://tools.ietf.org/html/rfc6184
+//
+// +---------------+
+// |0|1|2|3|4|5|6|7|
+// +-+-+-+-+-+-+-+-+
+// |F|NRI|  Type   |
+// +---------------+
+//
+// Single NAL unit and non-interleaved mode: single NAL unit packets,
+// STAP-A and FU-A. The interleaved mode's STAP-B, MTAP and FU-B are
+// rejected.
+
+use std::collections::VecDeque;
+
+use rtp;
+use sdp::Fmtp;
+use digest::base64_decode;
+use media::{ AccessUnit, Depacketizer as DepacketizerTrait, Sequence };
+use error::{ Error, Result };
+
+pub const SLICE: u8 = 1;
+pub const IDR: u8 = 5;
+pub const SEI: u8 = 6;
+pub const SPS: u8 = 7;
+pub const PPS: u8 = 8;
+pub const AUD: u8 = 9;
+pub const STAP_A: u8 = 24;
+pub const STAP_B: u8 = 25;
+pub const MTAP16: u8 = 26;
+pub const MTAP24: u8 = 27;
+pub const FU_A: u8 = 28;
+pub const FU_B: u8 = 29;
+
+/// The `a=fmtp` parameters of an H.264 stream.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Parameters {
+    /// profile_idc, the constraint flags and level_idc.
+    pub profile_level_id  : Option<[u8; 3]>,
+    pub packetization_mode: u8,
+    pub sps               : Vec<Vec<u8>>,
+    pub pps               : Vec<Vec<u8>>
+}
+
+/// Reassembles access units from the RTP packets of an H.264 stream.
+#[derive(Debug, Clone, Default)]
+pub struct Depacketizer {
+    parameters: Parameters,
+    sequence  : Sequence,
+    timestamp : u32,
+    nals      : Vec<Vec<u8>>,
+    fragment  : Option<Vec<u8>>,
+    loss      : u32,
+    ready     : VecDeque<AccessUnit>
+}
+
+/// The type of a NAL unit from its header byte.
+pub fn nal_type(header: u8) -> u8 {
+    header & 0x1f
+}
+
+impl Parameters {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Result<Parameters> {
+        let mut parameters = Parameters::default();
+        if let Some(id) = fmtp.get("profile-level-id") {
+            let id = u32::from_str_radix(id.trim(), 16).ok().filter(|_| id.trim().len() == 6)
+                                                       .ok_or_else(|| invalid("Invalid profile-level-id"))?;
+            parameters.profile_level_id = Some([(id >> 16) as u8, (id >> 8) as u8, id as u8]);
+        }
+        if let Some(mode) = fmtp.get("packetization-mode") {
+            parameters.packetization_mode = mode.trim().parse().map_err(|_| invalid("Invalid packetization-mode"))?;
+        }
+        if let Some(sets) = fmtp.get("sprop-parameter-sets") {
+            for set in sets.split(',').filter(|set| !set.trim().is_empty()) {
+                let nal = base64_decode(set).ok_or_else(|| invalid("Invalid sprop-parameter-sets"))?;
+                match nal.first().map(|&header| nal_type(header)) {
+                    Some(SPS) => parameters.sps.push(nal),
+                    Some(PPS) => parameters.pps.push(nal),
+                    _ => return Err(invalid("sprop-parameter-sets holds a NAL unit that is not a SPS or PPS"))
+                }
+            }
+        }
+        if parameters.profile_level_id.is_none() {
+            parameters.profile_level_id = parameters.sps.first().filter(|sps| sps.len() >= 4)
+                                                    .map(|sps| [sps[1], sps[2], sps[3]]);
+        }
+        Ok(parameters)
+    }
+}
+
+impl Depacketizer {
+    pub fn new () -> Depacketizer {
+        Depacketizer::default()
+    }
+    pub fn with_parameters(parameters: Parameters) -> Depacketizer {
+        Depacketizer { parameters, ..Depacketizer::default() }
+    }
+    /// The parameter sets from the SDP, replaced by any sent in band.
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+    fn add_nal(&mut self, nal: Vec<u8>) {
+        if nal.is_empty() {
+            return
+        }
+        match nal_type(nal[0]) {
+            SPS if self.parameters.sps.first() != Some(&nal) => self.parameters.sps = vec![nal.clone()],
+            PPS if self.parameters.pps.first() != Some(&nal) => self.parameters.pps = vec![nal.clone()],
+            _ => ()
+        }
+        self.nals.push(nal);
+    }
+    fn finish(&mut self) {
+        // A fragmented NAL unit whose end never came is left out.
+        self.fragment = None;
+        if self.nals.is_empty() {
+            return
+        }
+        let nals = ::std::mem::take(&mut self.nals);
+        self.ready.push_back(AccessUnit {
+            timestamp: self.timestamp,
+            keyframe : nals.iter().any(|nal| nal_type(nal[0]) == IDR),
+            nals,
+            loss     : ::std::mem::take(&mut self.loss)
+        });
+    }
+}
+
+impl DepacketizerTrait for Depacketizer {
+    type Frame = AccessUnit;
+
+    fn push(&mut self, packet: &rtp::Packet) -> Result<()> {
+        let lost = match self.sequence.next(packet.sequence_number()) {
+            Some(lost) => lost,
+            None => return Ok(())
+        };
+        if lost > 0 {
+            // A fragmented NAL unit cannot be completed across the gap.
+            self.fragment = None;
+        }
+        self.loss += lost;
+        if (!self.nals.is_empty() || self.fragment.is_some()) && packet.timestamp() != self.timestamp {
+            self.finish();
+        }
+        self.timestamp = packet.timestamp();
+
+        let payload = packet.payload();
+        let header = *payload.first().ok_or_else(|| invalid("Empty H.264 payload"))?;
+        match nal_type(header) {
+            1..=23 => {
+                self.fragment = None;
+                self.add_nal(payload.to_vec());
+            },
+            STAP_A => {
+                let mut rest = &payload[1..];
+                while rest.len() >= 2 {
+                    let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                    if rest.len() < 2 + len {
+                        return Err(invalid("STAP-A unit exceeds the packet"))
+                    }
+                    self.add_nal(rest[2..2 + len].to_vec());
+                    rest = &rest[2 + len..];
+                }
+            },
+            FU_A => {
+                if payload.len() < 3 {
+                    return Err(invalid("FU-A packet is too short"))
+                }
+                let fu = payload[1];
+                let (start, end) = (fu & 0x80 != 0, fu & 0x40 != 0);
+                if start {
+                    let mut nal = Vec::with_capacity(payload.len() * 4);
+                    nal.push((header & 0xe0) | nal_type(fu));
+                    nal.extend_from_slice(&payload[2..]);
+                    self.fragment = Some(nal);
+                } else if let Some(ref mut nal) = self.fragment {
+                    nal.extend_from_slice(&payload[2..]);
+                }
+                // Middle and end fragments whose start was lost are dropped,
+                // the gap was already counted.
+                if end {
+                    if let Some(nal) = self.fragment.take() {
+                        self.add_nal(nal);
+                    }
+                }
+            },
+            STAP_B | MTAP16 | MTAP24 | FU_B => return Err(invalid("Interleaved H.264 packetization is not supported")),
+            _ => return Err(invalid("Reserved H.264 NAL unit type"))
+        }
+        if packet.marker() {
+            self.finish();
+        }
+        Ok(())
+    }
+    fn pull(&mut self) -> Option<AccessUnit> {
+        self.ready.pop_front()
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Media(reason.to_string())
+}
+
+
+
+#[test]
+fn test() {
+    let fmtp: Fmtp = "96 profile-level-id=420029; packetization-mode=1; sprop-parameter-sets=Z00AKpWoHgCJ+WEAAAcIAAFfkAQ=,aO48gA==".parse().unwrap();
+    let parameters = Parameters::from_fmtp(&fmtp).unwrap();
+    assert_eq!(parameters.profile_level_id, Some([0x42, 0x00, 0x29]));
+    assert_eq!(parameters.packetization_mode, 1);
+    assert_eq!(parameters.sps[0][..4], [0x67, 0x4d, 0x00, 0x2a]);
+    assert_eq!(parameters.pps, vec![vec![0x68, 0xee, 0x3c, 0x80]]);
+
+    let mut depacketizer = Depacketizer::with_parameters(parameters.clone());
+    let mut push = |seq: u16, timestamp: u32, marker: bool, payload: &[u8]| {
+        let mut builder = rtp::PacketBuilder::new(96, seq, timestamp, 1);
+        builder.marker = marker;
+        let buf = builder.build(payload).unwrap();
+        depacketizer.push(&rtp::Packet::parse(&buf).unwrap()).unwrap();
+        depacketizer.pull()
+    };
+    // SPS and PPS in a STAP-A, then an IDR slice in three fragments.
+    let sps = &parameters.sps[0];
+    let mut stap = vec![0x18, 0, sps.len() as u8];
+    stap.extend_from_slice(sps);
+    stap.extend_from_slice(&[0, 4, 0x68, 0xee, 0x3c, 0x80]);
+    assert_eq!(push(1, 3000, false, &stap), None);
+    assert_eq!(push(2, 3000, false, &[0x7c, 0x85, 1, 2]), None);
+    assert_eq!(push(3, 3000, false, &[0x7c, 0x05, 3, 4]), None);
+    let idr = push(4, 3000, true, &[0x7c, 0x45, 5]).unwrap();
+    assert_eq!(idr.timestamp, 3000);
+    assert!(idr.keyframe);
+    assert_eq!(idr.loss, 0);
+    assert_eq!(idr.nals.len(), 3);
+    assert_eq!(idr.nals[2], vec![0x65, 1, 2, 3, 4, 5]);
+    assert!(idr.annex_b().starts_with(&[0, 0, 0, 1, 0x67]));
+
+    // A lost packet, a late one and a duplicate.
+    assert_eq!(push(6, 6000, true, &[0x41, 9]).unwrap(), AccessUnit { timestamp: 6000, nals: vec![vec![0x41, 9]], keyframe: false, loss: 1 });
+    assert_eq!(push(5, 6000, false, &[0x41, 8]), None);
+    assert_eq!(push(6, 6000, true, &[0x41, 9]), None);
+
+    // The end fragment lost; the access unit is cut at the next timestamp.
+    assert_eq!(push(7, 9000, false, &[0x41, 1]), None);
+    assert_eq!(push(8, 9000, false, &[0x5c, 0x81, 2]), None);
+    let partial = push(10, 12000, false, &[0x41, 3]).unwrap();
+    assert_eq!(partial.nals, vec![vec![0x41, 1]]);
+    assert_eq!(partial.loss, 1);
+}
@@ -0,0 +1,287 @@
+// RTP Payload Format for High Efficiency Video Coding (HEVC)
+// https://tools.ietf.org/html/rfc7798
+//
+// +---------------+---------------+
+// |0|1|2|3|4|5|6|7|0|1|2|3|4|5|6|7|
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |F|   Type    |  LayerId  | TID |
+// +-------------+-----------------+
+//
+// Single NAL unit packets, aggregation packets and fragmentation units.
+// When `sprop-max-don-diff` is above 0 every NAL unit carries a decoding
+// order number (DONL, DOND), which orders the units of an access unit.
+// Senders may then also interleave access units, which would need a
+// de-packetization buffer; their timestamps go backwards, and such packets
+// are rejected rather than cut into broken access units.
+
+use std::collections::VecDeque;
+
+use rtp;
+use sdp::Fmtp;
+use digest::base64_decode;
+use media::{ AccessUnit, Depacketizer as DepacketizerTrait, Sequence };
+use error::{ Error, Result };
+
+pub const BLA_W_LP: u8 = 16;
+pub const RSV_IRAP_23: u8 = 23;
+pub const VPS: u8 = 32;
+pub const SPS: u8 = 33;
+pub const PPS: u8 = 34;
+pub const AUD: u8 = 35;
+pub const AP: u8 = 48;
+pub const FU: u8 = 49;
+pub const PACI: u8 = 50;
+
+/// The `a=fmtp` parameters of an H.265 stream.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Parameters {
+    pub vps         : Vec<Vec<u8>>,
+    pub sps         : Vec<Vec<u8>>,
+    pub pps         : Vec<Vec<u8>>,
+    pub max_don_diff: u16
+}
+
+/// Reassembles access units from the RTP packets of an H.265 stream.
+#[derive(Debug, Clone, Default)]
+pub struct Depacketizer {
+    parameters: Parameters,
+    sequence  : Sequence,
+    timestamp : u32,
+    started   : bool,
+    // Each NAL unit with its decoding order number, when there is one.
+    nals      : Vec<(u16, Vec<u8>)>,
+    fragment  : Option<(u16, Vec<u8>)>,
+    loss      : u32,
+    ready     : VecDeque<AccessUnit>
+}
+
+/// The type of a NAL unit from the first byte of its header.
+pub fn nal_type(header: u8) -> u8 {
+    (header >> 1) & 0x3f
+}
+
+impl Parameters {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Result<Parameters> {
+        let mut parameters = Parameters::default();
+        for &(name, kind) in &[("sprop-vps", VPS), ("sprop-sps", SPS), ("sprop-pps", PPS)] {
+            for set in fmtp.get(name).unwrap_or("").split(',').filter(|set| !set.trim().is_empty()) {
+                let nal = base64_decode(set).ok_or_else(|| invalid("Invalid H.265 parameter set"))?;
+                if nal.len() < 2 || nal_type(nal[0]) != kind {
+                    return Err(invalid("H.265 parameter set holds the wrong NAL unit type"))
+                }
+                match kind {
+                    VPS => parameters.vps.push(nal),
+                    SPS => parameters.sps.push(nal),
+                    _ => parameters.pps.push(nal)
+                }
+            }
+        }
+        if let Some(diff) = fmtp.get("sprop-max-don-diff") {
+            parameters.max_don_diff = diff.trim().parse().map_err(|_| invalid("Invalid sprop-max-don-diff"))?;
+        }
+        Ok(parameters)
+    }
+    /// Whether packets carry decoding order numbers.
+    pub fn has_donl(&self) -> bool {
+        self.max_don_diff > 0
+    }
+}
+
+impl Depacketizer {
+    pub fn new () -> Depacketizer {
+        Depacketizer::default()
+    }
+    pub fn with_parameters(parameters: Parameters) -> Depacketizer {
+        Depacketizer { parameters, ..Depacketizer::default() }
+    }
+    /// The parameter sets from the SDP, replaced by any sent in band.
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+    fn add_nal(&mut self, don: u16, nal: Vec<u8>) {
+        if nal.len() < 2 {
+            return
+        }
+        match nal_type(nal[0]) {
+            VPS if self.parameters.vps.first() != Some(&nal) => self.parameters.vps = vec![nal.clone()],
+            SPS if self.parameters.sps.first() != Some(&nal) => self.parameters.sps = vec![nal.clone()],
+            PPS if self.parameters.pps.first() != Some(&nal) => self.parameters.pps = vec![nal.clone()],
+            _ => ()
+        }
+        self.nals.push((don, nal));
+    }
+    fn finish(&mut self) {
+        // A fragmented NAL unit whose end never came is left out.
+        self.fragment = None;
+        if self.nals.is_empty() {
+            return
+        }
+        let mut nals = ::std::mem::take(&mut self.nals);
+        if self.parameters.has_donl() {
+            let first = nals[0].0;
+            nals.sort_by_key(|&(don, _)| don.wrapping_sub(first) as i16);
+        }
+        let nals: Vec<Vec<u8>> = nals.into_iter().map(|(_, nal)| nal).collect();
+        self.ready.push_back(AccessUnit {
+            timestamp: self.timestamp,
+            keyframe : nals.iter().any(|nal| (BLA_W_LP..=RSV_IRAP_23).contains(&nal_type(nal[0]))),
+            nals,
+            loss     : ::std::mem::take(&mut self.loss)
+        });
+    }
+}
+
+impl DepacketizerTrait for Depacketizer {
+    type Frame = AccessUnit;
+
+    fn push(&mut self, packet: &rtp::Packet) -> Result<()> {
+        let lost = match self.sequence.next(packet.sequence_number()) {
+            Some(lost) => lost,
+            None => return Ok(())
+        };
+        if lost > 0 {
+            // A fragmented NAL unit cannot be completed across the gap.
+            self.fragment = None;
+        }
+        self.loss += lost;
+        if self.parameters.has_donl() && self.started && (packet.timestamp().wrapping_sub(self.timestamp) as i32) < 0 {
+            return Err(invalid("Interleaved H.265 access units are not supported"))
+        }
+        if (!self.nals.is_empty() || self.fragment.is_some()) && packet.timestamp() != self.timestamp {
+            self.finish();
+        }
+        self.timestamp = packet.timestamp();
+        self.started = true;
+
+        let payload = packet.payload();
+        if payload.len() < 3 {
+            return Err(invalid("H.265 payload is too short"))
+        }
+        let donl = self.parameters.has_donl();
+        let don_at = |at: usize| -> Result<u16> {
+            if donl {
+                payload.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+                                       .ok_or_else(|| invalid("H.265 DONL field exceeds the packet"))
+            } else {
+                Ok(0)
+            }
+        };
+        match nal_type(payload[0]) {
+            AP => {
+                let mut at = 2;
+                let mut don = 0u16;
+                let mut first = true;
+                while at < payload.len() {
+                    if donl {
+                        if first {
+                            don = don_at(at)?;
+                            at += 2;
+                        } else {
+                            don = don.wrapping_add(u16::from(payload[at]) + 1);
+                            at += 1;
+                        }
+                    }
+                    first = false;
+                    let len = payload.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                                     .ok_or_else(|| invalid("Aggregation unit exceeds the packet"))?;
+                    let nal = payload.get(at + 2..at + 2 + len).ok_or_else(|| invalid("Aggregation unit exceeds the packet"))?;
+                    self.add_nal(don, nal.to_vec());
+                    at += 2 + len;
+                }
+            },
+            FU => {
+                let fu = payload[2];
+                let (start, end) = (fu & 0x80 != 0, fu & 0x40 != 0);
+                if start {
+                    let don = don_at(3)?;
+                    let data = payload.get(if donl { 5 } else { 3 }..).unwrap_or(&[]);
+                    let mut nal = Vec::with_capacity(payload.len() * 4);
+                    nal.push((payload[0] & 0x81) | (fu & 0x3f) << 1);
+                    nal.push(payload[1]);
+                    nal.extend_from_slice(data);
+                    self.fragment = Some((don, nal));
+                } else if let Some((_, ref mut nal)) = self.fragment {
+                    nal.extend_from_slice(&payload[3..]);
+                }
+                // Fragments whose start was lost are dropped, the gap was
+                // already counted.
+                if end {
+                    if let Some((don, nal)) = self.fragment.take() {
+                        self.add_nal(don, nal);
+                    }
+                }
+            },
+            PACI => return Err(invalid("H.265 PACI packets are not supported")),
+            51..=63 => return Err(invalid("Reserved H.265 NAL unit type")),
+            _ => {
+                self.fragment = None;
+                let don = don_at(2)?;
+                let mut nal = payload[..2].to_vec();
+                nal.extend_from_slice(&payload[if donl { 4 } else { 2 }..]);
+                self.add_nal(don, nal);
+            }
+        }
+        if packet.marker() {
+            self.finish();
+        }
+        Ok(())
+    }
+    fn pull(&mut self) -> Option<AccessUnit> {
+        self.ready.pop_front()
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Media(reason.to_string())
+}
+
+
+
+#[test]
+fn test() {
+    // VPS, SPS and PPS headers with made up bodies.
+    let fmtp: Fmtp = "96 sprop-vps=QAEMAf//; sprop-sps=QgEBAWA=; sprop-pps=RAHA8g==".parse().unwrap();
+    let parameters = Parameters::from_fmtp(&fmtp).unwrap();
+    assert_eq!(parameters.vps, vec![vec![0x40, 0x01, 0x0c, 0x01, 0xff, 0xff]]);
+    assert_eq!(parameters.sps, vec![vec![0x42, 0x01, 0x01, 0x01, 0x60]]);
+    assert_eq!(parameters.pps, vec![vec![0x44, 0x01, 0xc0, 0xf2]]);
+    assert!(!parameters.has_donl());
+
+    let push = |depacketizer: &mut Depacketizer, seq: u16, marker: bool, payload: &[u8]| {
+        let mut builder = rtp::PacketBuilder::new(96, seq, 9000, 1);
+        builder.marker = marker;
+        let buf = builder.build(payload).unwrap();
+        depacketizer.push(&rtp::Packet::parse(&buf).unwrap()).unwrap();
+        depacketizer.pull()
+    };
+    // An aggregation packet with the parameter sets, then an IDR_W_RADL
+    // (type 19) slice in two fragmentation units.
+    let mut depacketizer = Depacketizer::with_parameters(parameters);
+    let ap = [0x60, 0x01, 0, 2, 0x40, 0x01, 0, 2, 0x42, 0x01, 0, 3, 0x44, 0x01, 0xc0];
+    assert_eq!(push(&mut depacketizer, 1, false, &ap), None);
+    assert_eq!(push(&mut depacketizer, 2, false, &[0x62, 0x01, 0x93, 1, 2]), None);
+    let au = push(&mut depacketizer, 3, true, &[0x62, 0x01, 0x53, 3]).unwrap();
+    assert!(au.keyframe);
+    assert_eq!(au.loss, 0);
+    assert_eq!(au.nals.len(), 4);
+    assert_eq!(au.nals[3], vec![0x26, 0x01, 1, 2, 3]);
+    assert_eq!(depacketizer.parameters().pps, vec![vec![0x44, 0x01, 0xc0]]);
+
+    // With DONL the units of an access unit are put in decoding order.
+    let mut depacketizer = Depacketizer::with_parameters(Parameters { max_don_diff: 2, ..Parameters::default() });
+    assert_eq!(push(&mut depacketizer, 1, false, &[0x02, 0x01, 0, 0, 0xb]), None);
+    let ap = [0x60, 0x01, 0xff, 0xff, 0, 3, 0x02, 0x01, 0xa, 1, 0, 3, 0x02, 0x01, 0xc];
+    let au = push(&mut depacketizer, 2, true, &ap).unwrap();
+    assert!(!au.keyframe);
+    assert_eq!(au.nals, vec![vec![0x02, 0x01, 0xa], vec![0x02, 0x01, 0xb], vec![0x02, 0x01, 0xc]]);
+
+    // A unit of an earlier access unit after a later one is interleaving.
+    let mut builder = rtp::PacketBuilder::new(96, 3, 12000, 1);
+    assert!(depacketizer.push(&rtp::Packet::parse(&builder.build(&[0x02, 0x01, 0, 3, 0xd]).unwrap()).unwrap()).is_ok());
+    builder.sequence_number = 4;
+    builder.timestamp = 9000;
+    match depacketizer.push(&rtp::Packet::parse(&builder.build(&[0x02, 0x01, 0, 2, 0xe]).unwrap()).unwrap()) {
+        Err(Error::Media(_)) => (),
+        other => panic!("{:?}", other)
+    }
+}
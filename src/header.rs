@@ -1,62 +1,148 @@
-
-use std::string::ToString;
-use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{ self, Write };
 
 pub type Key   = String;
 pub type Value = String;
 
-#[derive(Debug)]
+// Header fields in the order they were added, with the name spelled the way
+// it was received. Names compare case-insensitively and may repeat, as
+// `WWW-Authenticate` and `Via` do.
+// https://tools.ietf.org/html/rfc2326#section-4.2
+
+#[derive(Debug, Clone, Default)]
 pub struct Headers {
-    headers: BTreeMap<Key, Value>,
+    headers: Vec<(Key, Value)>,
 }
 
 impl Headers{
     pub fn new () -> Headers {
-        Headers { headers: BTreeMap::new() }
+        Headers { headers: Vec::new() }
+    }
+    /// The first value of the field `key`.
+    pub fn get<K: AsRef<str>>(&self, key: K) -> Option<&Value> {
+        let key = key.as_ref();
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v)
     }
-    pub fn get(&self, key: Key) -> Option<&Value> {
-        self.headers.get(key.as_str())
+    /// Every value of the field `key`, in order.
+    pub fn get_all<K: AsRef<str>>(&self, key: K) -> Vec<&Value> {
+        let key = key.as_ref();
+        self.headers.iter().filter(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v).collect()
     }
-    pub fn insert(&mut self, _key: Key, value: Value) -> Option<Value> {
+    pub fn contains<K: AsRef<str>>(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+    /// Sets the field `key` to a single value.
+    ///
+    /// The value takes the place of the first existing field of that name
+    /// and any further ones are dropped. Well-known names are stored in their
+    /// canonical casing.
+    pub fn insert<K: Into<Key>, V: Into<Value>>(&mut self, key: K, value: V) -> Option<Value> {
         // If the map did not have this key present, None is returned.
         // If the map did have this key present, the value is updated, and the old value is returned.
-        let key = _key.to_lowercase();
-        if self.headers.contains_key(&key) {
-            self.remove(&key);
+        let key = canonical(key.into());
+        match self.headers.iter().position(|(k, _)| k.eq_ignore_ascii_case(&key)) {
+            Some(i) => {
+                let old = ::std::mem::replace(&mut self.headers[i], (key, value.into()));
+                let mut j = i + 1;
+                while j < self.headers.len() {
+                    if self.headers[j].0.eq_ignore_ascii_case(&old.0) {
+                        self.headers.remove(j);
+                    } else {
+                        j += 1;
+                    }
+                }
+                Some(old.1)
+            },
+            None => {
+                self.headers.push((key, value.into()));
+                None
+            }
         }
-        self.headers.insert(key, value)
     }
-    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+    /// Adds another field `key`, keeping the ones already present.
+    pub fn append<K: Into<Key>, V: Into<Value>>(&mut self, key: K, value: V) {
+        self.headers.push((canonical(key.into()), value.into()));
+    }
+    /// Adds a field exactly as received from the peer.
+    pub(crate) fn append_raw(&mut self, key: Key, value: Value) {
+        self.headers.push((key, value));
+    }
+    /// Removes every field `key`, returning the first value.
+    pub fn remove<K: AsRef<str>>(&mut self, key: K) -> Option<Value> {
         // If the map did have this key present, returning the value at the key if the key was previously in the map
         // If the map did not have this key present, return None.
-        self.headers.remove(key)
+        let key = key.as_ref();
+        let mut first = None;
+        let mut i = 0;
+        while i < self.headers.len() {
+            if self.headers[i].0.eq_ignore_ascii_case(key) {
+                let (_, value) = self.headers.remove(i);
+                first = first.or(Some(value));
+            } else {
+                i += 1;
+            }
+        }
+        first
     }
     pub fn keys(&self) -> Vec<Key> {
-        self.headers.keys().cloned().collect::<Vec<Key>>()
+        self.headers.iter().map(|(k, _)| k.clone()).collect::<Vec<Key>>()
     }
     pub fn values(&self) -> Vec<Value> {
-        self.headers.values().cloned().collect::<Vec<Value>>()
+        self.headers.iter().map(|(_, v)| v.clone()).collect::<Vec<Value>>()
+    }
+    /// The fields in order, names as stored.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
     }
+    /// The number of fields, counting repeated names once per field.
     pub fn len(&self) -> usize {
         self.headers.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
     pub fn clear(&mut self) {
         self.headers.clear();
     }
+    /// Writes the headers in wire format, leaving out the names in `skip`.
+    pub fn write_to<W: Write>(&self, w: &mut W, skip: &[&str]) -> io::Result<()> {
+        for (key, value) in &self.headers {
+            if skip.iter().any(|name| name.eq_ignore_ascii_case(key)) {
+                continue;
+            }
+            write!(w, "{}: {}\r\n", key, value)?;
+        }
+        Ok(())
+    }
 }
 
-impl ToString for Headers {
-    fn to_string(&self) -> String {
-        let mut s = String::new();
+// https://tools.ietf.org/html/rfc2326#section-12
+const WELL_KNOWN: &[&str] = &[
+    "Accept", "Accept-Encoding", "Accept-Language", "Accept-Ranges", "Allow",
+    "Authentication-Info", "Authorization", "Bandwidth", "Blocksize", "Cache-Control",
+    "Conference", "Connection", "Content-Base", "Content-Encoding", "Content-Language",
+    "Content-Length", "Content-Location", "Content-Type", "CSeq", "Date", "Expires",
+    "From", "Host", "If-Match", "If-Modified-Since", "Last-Modified", "Location",
+    "Proxy-Authenticate", "Proxy-Authorization", "Proxy-Require", "Public", "Range",
+    "Referer", "Require", "Retry-After", "RTP-Info", "Scale", "Server", "Session",
+    "Speed", "Timestamp", "Transport", "Unsupported", "User-Agent", "Vary", "Via",
+    "WWW-Authenticate"
+];
+
+/// The canonical spelling of a well-known header name, `key` itself otherwise.
+fn canonical(key: Key) -> Key {
+    match WELL_KNOWN.iter().find(|name| name.eq_ignore_ascii_case(&key)) {
+        Some(name) => name.to_string(),
+        None => key
+    }
+}
+
+impl fmt::Display for Headers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for (key, value) in &self.headers {
-            let mut key_chars = key.chars();
-            let mut uppercase_key = String::new();
-            uppercase_key.push_str(key_chars.nth(0).unwrap().to_string().to_uppercase().as_ref());
-            uppercase_key.push_str(key_chars.as_str());
-            let line = uppercase_key + ": " + value.as_ref() + "\r\n";
-            s.push_str(line.as_ref())
+            write!(f, "{}: {}\r\n", key, value)?;
         }
-        s
+        Ok(())
     }
 }
 
@@ -70,19 +156,39 @@ fn test() {
     headers.insert("content-type".to_string(), "video/mp4".to_string());
     headers.insert("content-length".to_string(), "512".to_string());
 
-    assert_eq!(headers.keys(), vec!["content-length", "content-type"] );
-    assert_eq!(headers.values(), vec!["512", "video/mp4"] );
+    assert_eq!(headers.keys(), vec!["Content-Type", "Content-Length"] );
+    assert_eq!(headers.values(), vec!["video/mp4", "512"] );
 
-    assert_eq!(headers.get("content-type".to_string()), Some(&"video/mp4".to_string()) );
-    assert_eq!(headers.get("content-length".to_string()), Some(&"512".to_string()) );
-    assert_eq!(headers.get("None".to_string()), None );
+    assert_eq!(headers.get("content-type"), Some(&"video/mp4".to_string()) );
+    assert_eq!(headers.get("content-length"), Some(&"512".to_string()) );
+    assert_eq!(headers.get("None"), None );
 
-    assert_eq!(headers.to_string(), "Content-length: 512\r\nContent-type: video/mp4\r\n");
+    assert_eq!(headers.to_string(), "Content-Type: video/mp4\r\nContent-Length: 512\r\n");
 
-    headers.remove(&"content-type".to_string());
-    assert_eq!(headers.get("content-type".to_string()), None );
+    headers.remove("content-type");
+    assert_eq!(headers.get("content-type"), None );
 
     headers.clear();
-    assert_eq!(headers.get("content-length".to_string()), None );
+    assert_eq!(headers.get("content-length"), None );
     assert_eq!(headers.len(), 0);
-}
\ No newline at end of file
+}
+
+#[test]
+fn repeated() {
+    let mut headers = Headers::new();
+    headers.append("www-authenticate", "Digest realm=\"cam\", nonce=\"abc\"");
+    headers.append_raw("X-Vendor-ID".to_string(), "1".to_string());
+    headers.append("WWW-Authenticate", "Basic realm=\"cam\"");
+    headers.append("Via", "RTSP/1.0 proxy1");
+
+    assert_eq!(headers.len(), 4);
+    assert_eq!(headers.get("WWW-AUTHENTICATE"), Some(&"Digest realm=\"cam\", nonce=\"abc\"".to_string()));
+    assert_eq!(headers.get_all("www-authenticate"), vec!["Digest realm=\"cam\", nonce=\"abc\"", "Basic realm=\"cam\""]);
+    assert_eq!(headers.keys(), vec!["WWW-Authenticate", "X-Vendor-ID", "WWW-Authenticate", "Via"]);
+
+    // `insert` collapses repeated fields into one, keeping its position.
+    assert_eq!(headers.insert("WWW-Authenticate", "Basic realm=\"x\""), Some("Digest realm=\"cam\", nonce=\"abc\"".to_string()));
+    assert_eq!(headers.to_string(), "WWW-Authenticate: Basic realm=\"x\"\r\nX-Vendor-ID: 1\r\nVia: RTSP/1.0 proxy1\r\n");
+    assert_eq!(headers.remove("x-vendor-id"), Some("1".to_string()));
+    assert!(!headers.contains("X-Vendor-ID"));
+}
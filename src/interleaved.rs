@@ -0,0 +1,112 @@
+// Embedded (Interleaved) Binary Data
+// https://tools.ietf.org/html/rfc2326#section-10.12
+//
+// Stream data such as RTP packets is encapsulated by an ASCII dollar sign
+// (24 hexadecimal), followed by a one-byte channel identifier, followed by
+// the length of the encapsulated binary data as a binary, two-byte integer
+// in network byte order.
+//
+//   S->C: $\000{2 byte length}{"length" bytes data, w/RTP header}
+
+use std::io::Write;
+
+use error::{ Error, Result };
+
+/// The largest payload the two-byte length can describe.
+pub const MAX_PAYLOAD_SIZE: usize = 0xffff;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    channel: u8,
+    payload: Vec<u8>
+}
+
+impl Frame {
+    /// Fails with `Error::TooLarge` when the payload exceeds `MAX_PAYLOAD_SIZE`.
+    pub fn new (channel: u8, payload: Vec<u8>) -> Result<Frame> {
+        if payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(Error::TooLarge)
+        }
+        Ok(Frame { channel, payload })
+    }
+    /// Parses one frame from the front of `buf`, which must start with `$`.
+    ///
+    /// Returns the frame and the number of bytes it occupied, or
+    /// `Error::Incomplete` when more bytes are needed.
+    pub fn parse(buf: &[u8]) -> Result<(Frame, usize)> {
+        if buf.is_empty() {
+            return Err(Error::Incomplete)
+        }
+        if buf[0] != b'$' {
+            return Err(Error::Header)
+        }
+        if buf.len() < 4 {
+            return Err(Error::Incomplete)
+        }
+        let len = 4 + ((buf[2] as usize) << 8 | buf[3] as usize);
+        if buf.len() < len {
+            return Err(Error::Incomplete)
+        }
+        Ok((Frame { channel: buf[1], payload: buf[4..len].to_vec() }, len))
+    }
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+    pub fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+    /// Appends the wire form of the frame to `buf`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&header(self.channel, self.payload.len()));
+        buf.extend_from_slice(&self.payload);
+    }
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        write(w, self.channel, &self.payload)
+    }
+}
+
+/// Writes `payload` on `channel` without building a `Frame` first.
+///
+/// The header and payload go out in a single `write_all`, so frames from
+/// different threads sharing a locked writer never interleave.
+pub fn write<W: Write>(w: &mut W, channel: u8, payload: &[u8]) -> Result<()> {
+    if payload.len() > MAX_PAYLOAD_SIZE {
+        return Err(Error::TooLarge)
+    }
+    let mut buf = Vec::with_capacity(4 + payload.len());
+    buf.extend_from_slice(&header(channel, payload.len()));
+    buf.extend_from_slice(payload);
+    w.write_all(&buf)?;
+    Ok(())
+}
+
+fn header(channel: u8, len: usize) -> [u8; 4] {
+    [b'$', channel, (len >> 8) as u8, len as u8]
+}
+
+
+
+#[test]
+fn test() {
+    let frame = Frame::new(1, vec![0x80, 0x60, 0x00, 0x01]).unwrap();
+    let mut buf = Vec::new();
+    frame.encode(&mut buf);
+    assert_eq!(buf, vec![b'$', 1, 0, 4, 0x80, 0x60, 0x00, 0x01]);
+
+    let mut written = Vec::new();
+    write(&mut written, 1, frame.payload()).unwrap();
+    assert_eq!(written, buf);
+
+    assert_eq!(Frame::parse(&buf).unwrap(), (frame, 8));
+    match Frame::parse(&buf[..5]) {
+        Err(Error::Incomplete) => (),
+        other => panic!("{:?}", other)
+    }
+    match Frame::new(0, vec![0; MAX_PAYLOAD_SIZE + 1]) {
+        Err(Error::TooLarge) => (),
+        other => panic!("{:?}", other)
+    }
+}
@@ -1,14 +1,14 @@
-/// rtsp_URL  =   ( "rtsp:" | "rtspu:" ) "//" host [ ":" port ] [ abs_path ]
-
-/// RTSP URL Scheme
-/// tcp: rtsp://  | udp: rtspu://
-
-/// RTSP Port
-/// Default port 554
-
-/// For example, the RTSP URL:
-/// rtsp://media.example.com:554/twister/audiotrack
-/// rtspu://media.example.com:554/twister/audiotrack
+//! rtsp_URL  =   ( "rtsp:" | "rtspu:" ) "//" host [ ":" port ] [ abs_path ]
+//!
+//! RTSP URL Scheme
+//! tcp: rtsp://  | udp: rtspu://  | tls: rtsps://
+//!
+//! RTSP Port
+//! Default port 554, 322 for rtsps
+//!
+//! For example, the RTSP URL:
+//! rtsp://media.example.com:554/twister/audiotrack
+//! rtspu://media.example.com:554/twister/audiotrack
 
 
 
@@ -16,9 +16,29 @@ pub mod status;
 pub mod method;
 pub mod version;
 pub mod header;
+pub mod transport;
+pub mod session;
+pub mod range;
+pub mod rtp_info;
+pub mod url;
+pub mod sdp;
+pub mod state;
+pub mod auth;
 
 pub mod request;
 pub mod response;
+pub mod codec;
+pub mod interleaved;
+pub mod rtp;
+pub mod rtcp;
+pub mod media;
+pub mod h264;
+pub mod h265;
+pub mod aac;
+pub mod audio;
+mod parse;
+mod digest;
+mod random;
 
 pub mod error;
 
@@ -0,0 +1,91 @@
+// Media payload formats: reassembling the frames a codec carries over RTP.
+// https://tools.ietf.org/html/rfc3550#section-5.1
+//
+// A depacketizer is fed the packets of one stream in arrival order and
+// hands back complete frames. Gaps in the sequence numbers are counted
+// and reported on the next frame, so a decoder knows where it may have
+// to resynchronize.
+
+use rtp;
+use error::Result;
+
+pub trait Depacketizer {
+    type Frame;
+
+    /// Adds the next packet of the stream.
+    fn push(&mut self, packet: &rtp::Packet) -> Result<()>;
+    /// The next complete frame, if any.
+    fn pull(&mut self) -> Option<Self::Frame>;
+}
+
+/// A video access unit: the NAL units of one picture, without start codes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AccessUnit {
+    pub timestamp: u32,
+    pub nals     : Vec<Vec<u8>>,
+    /// The picture can be decoded without any before it.
+    pub keyframe : bool,
+    /// Packets lost since the previous access unit or within this one. A
+    /// NAL unit that lost a fragment is left out, without adding to this.
+    pub loss     : u32
+}
+
+/// An encoded audio frame.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AudioFrame {
+    pub timestamp: u32,
+    pub data     : Vec<u8>,
+    /// Packets lost since the previous frame or within this one. A frame
+    /// that lost a fragment is left out, without adding to this.
+    pub loss     : u32
+}
+
+/// Tracks sequence numbers to find lost, late and duplicate packets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sequence {
+    last: Option<u16>
+}
+
+impl AccessUnit {
+    /// The NAL units each after a `00 00 00 01` start code.
+    pub fn annex_b(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.nals.iter().map(|nal| nal.len() + 4).sum());
+        for nal in &self.nals {
+            buf.extend_from_slice(&[0, 0, 0, 1]);
+            buf.extend_from_slice(nal);
+        }
+        buf
+    }
+    /// The NAL units each after its 4 byte big endian length, as in MP4.
+    pub fn length_prefixed(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.nals.iter().map(|nal| nal.len() + 4).sum());
+        for nal in &self.nals {
+            buf.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+            buf.extend_from_slice(nal);
+        }
+        buf
+    }
+}
+
+impl Sequence {
+    pub fn new () -> Sequence {
+        Sequence { last: None }
+    }
+    /// The number of packets missing before `seq`, or `None` when the
+    /// packet is a duplicate or arrived after a later one and should be
+    /// dropped.
+    pub fn next(&mut self, seq: u16) -> Option<u32> {
+        let lost = match self.last {
+            Some(last) => {
+                let delta = seq.wrapping_sub(last);
+                if delta == 0 || delta >= 0x8000 {
+                    return None
+                }
+                u32::from(delta - 1)
+            },
+            None => 0
+        };
+        self.last = Some(seq);
+        Some(lost)
+    }
+}
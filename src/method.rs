@@ -1,12 +1,14 @@
 
+use std::collections::HashSet;
 use std::fmt;
 use std::str::FromStr;
 use std::convert::AsRef;
 use error::Error;
 
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub enum Method {
+    #[default]
     Options,
     Describe,
     Setup,
@@ -24,19 +26,17 @@ pub enum Method {
 impl Method {
     pub fn is_s_to_c(&self) -> bool {
         // https://tools.ietf.org/html/rfc2326#section-10
-        match *self {
+        matches!(*self,
             Method::Options
             | Method::Announce
             | Method::GetParameter
             | Method::SetParameter
             | Method::Redirect
-            | Method::Extension(..) => true,
-            _ => false
-        }
+            | Method::Extension(..))
     }
     pub fn is_c_to_s(&self) -> bool {
         // https://tools.ietf.org/html/rfc2326#section-10
-        match *self {
+        matches!(*self,
             Method::Options
             | Method::Describe
             | Method::Announce
@@ -47,9 +47,7 @@ impl Method {
             | Method::Setup
             | Method::SetParameter
             | Method::Teardown
-            | Method::Extension(..) => true,
-            _ => false
-        }
+            | Method::Extension(..))
     }
 }
 impl AsRef<str> for Method {
@@ -74,7 +72,7 @@ impl AsRef<str> for Method {
 impl FromStr for Method {
     type Err = Error;
     fn from_str(s: &str) -> Result<Method, Error> {
-        if s == "" {
+        if s.is_empty() {
             Err(Error::Method)
         } else {
             Ok(match s {
@@ -115,8 +113,13 @@ impl fmt::Display for Method {
     }
 }
 
-impl Default for Method {
-    fn default() -> Method {
-        Method::Options
-    }
-}
\ No newline at end of file
+/// Parses the method list of a `Public` or `Allow` header, skipping
+/// empty entries.
+pub fn parse_list(s: &str) -> HashSet<Method> {
+    s.split(',').map(str::trim).filter_map(|method| method.parse().ok()).collect()
+}
+
+/// Formats methods for a `Public` or `Allow` header.
+pub fn join(methods: &[Method]) -> String {
+    methods.iter().map(|method| method.to_string()).collect::<Vec<String>>().join(", ")
+}
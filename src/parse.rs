@@ -0,0 +1,163 @@
+// Helpers shared by the request and response parsers.
+//
+// https://tools.ietf.org/html/rfc2326#section-4
+// https://tools.ietf.org/html/rfc2326#section-6
+// https://tools.ietf.org/html/rfc2326#section-7
+
+use std::str;
+
+use error::{ Error, Result };
+use header::Headers;
+
+/// Upper bound for the start line plus headers of a single message.
+pub const MAX_HEAD_SIZE: usize = 16 * 1024;
+/// Upper bound for the `Content-Length` of a single message.
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// A message head that has been split off the front of a buffer.
+pub struct Head<'a> {
+    /// The start line (`Request-Line` or `Status-Line`).
+    pub start_line: &'a str,
+    pub headers: Headers,
+    /// Offset of the first body byte in the buffer.
+    pub body_start: usize
+}
+
+impl<'a> Head<'a> {
+    /// Returns the body length announced by `Content-Length`, `0` when absent.
+    pub fn content_length(&self) -> Result<usize> {
+        match self.headers.get("Content-Length") {
+            None => Ok(0),
+            Some(value) => {
+                let len = value.trim().parse::<usize>().map_err(|_| Error::Header)?;
+                if len > MAX_BODY_SIZE {
+                    return Err(Error::TooLarge)
+                }
+                Ok(len)
+            }
+        }
+    }
+    /// Returns the body and the total number of bytes taken by the message.
+    pub fn body(&self, buf: &'a [u8]) -> Result<(&'a [u8], usize)> {
+        let end = self.body_start + self.content_length()?;
+        if buf.len() < end {
+            return Err(Error::Incomplete)
+        }
+        Ok((&buf[self.body_start..end], end))
+    }
+}
+
+/// Splits the head off `buf`.
+///
+/// Empty lines in front of the start line are skipped, as RFC 2616 §4.1
+/// asks of robust implementations. Both `CRLF` and bare `LF` line endings
+/// are accepted because several camera vendors send the latter.
+pub fn head(buf: &[u8]) -> Result<Head<'_>> {
+    let start = skip_empty_lines(buf);
+    let end = match find_empty_line(&buf[start..]) {
+        Some((end, len)) => (start + end, start + end + len),
+        None => {
+            if buf.len() - start > MAX_HEAD_SIZE {
+                return Err(Error::TooLarge)
+            }
+            return Err(Error::Incomplete)
+        }
+    };
+    if end.0 - start > MAX_HEAD_SIZE {
+        return Err(Error::TooLarge)
+    }
+
+    let text = str::from_utf8(&buf[start..end.0])?;
+    let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+    let start_line = lines.next().ok_or(Error::Incomplete)?;
+
+    let mut headers = Headers::new();
+    let mut last: Option<(String, String)> = None;
+    for line in lines {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // Obsolete line folding, the line continues the previous value.
+            match last {
+                Some((_, ref mut value)) => {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(line.trim());
+                },
+                None => return Err(Error::Header)
+            }
+            continue;
+        }
+        if let Some((key, value)) = last.take() {
+            headers.append_raw(key, value);
+        }
+        last = Some(header_line(line)?);
+    }
+    if let Some((key, value)) = last.take() {
+        headers.append_raw(key, value);
+    }
+
+    Ok(Head { start_line, headers, body_start: end.1 })
+}
+
+/// Returns the number of `CR` and `LF` bytes at the front of `buf`.
+pub fn skip_empty_lines(buf: &[u8]) -> usize {
+    buf.iter().take_while(|&&b| b == b'\r' || b == b'\n').count()
+}
+
+/// Returns the length of the head at the front of `buf`, including the
+/// empty line that terminates it, or `None` when it is not complete yet.
+pub fn head_len(buf: &[u8]) -> Option<usize> {
+    let start = skip_empty_lines(buf);
+    find_empty_line(&buf[start..]).map(|(end, len)| start + end + len)
+}
+
+/// Returns the body length announced by the `Content-Length` of `head`,
+/// however large, `0` when absent and `None` when it is not a number.
+///
+/// Used to step over the body of a message that failed to parse, so the
+/// head is only looked at line by line.
+pub fn body_len(head: &[u8]) -> Option<usize> {
+    let text = String::from_utf8_lossy(head);
+    for line in text.lines() {
+        if let Some(colon) = line.find(':') {
+            if line[..colon].trim().eq_ignore_ascii_case("Content-Length") {
+                return line[colon + 1..].trim().parse().ok()
+            }
+        }
+    }
+    Some(0)
+}
+
+/// Parses `field-name ":" [ field-value ]`.
+fn header_line(line: &str) -> Result<(String, String)> {
+    let colon = line.find(':').ok_or(Error::Header)?;
+    let key = line[..colon].trim_end();
+    if !is_token(key) {
+        return Err(Error::Header)
+    }
+    Ok((key.to_string(), line[colon + 1..].trim().to_string()))
+}
+
+/// Finds the line terminator that closes the head, returning its offset and length.
+fn find_empty_line(buf: &[u8]) -> Option<(usize, usize)> {
+    let mut i = 0;
+    while i < buf.len() {
+        if buf[i] == b'\n' {
+            if buf[i + 1..].starts_with(b"\r\n") {
+                return Some((i + 1, 2))
+            }
+            if buf[i + 1..].starts_with(b"\n") {
+                return Some((i + 1, 1))
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// token = 1*<any CHAR except CTLs or tspecials>
+pub fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| {
+        b > 0x20 && b < 0x7f && !b"()<>@,;:\\\"/[]?={}".contains(&b)
+    })
+}
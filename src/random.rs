@@ -0,0 +1,25 @@
+// Random numbers for nonces, secrets and the starting points of RTP
+// streams.
+//
+// They are read from the operating system's /dev/urandom. Where there is
+// none they fall back to the clock hashed with the randomly keyed SipHash
+// of `RandomState`, which is hard to guess but no CSPRNG.
+
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{ BuildHasher, Hasher };
+use std::io::Read;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/// 64 random bits.
+pub fn random_u64() -> u64 {
+    let mut buf = [0u8; 8];
+    if File::open("/dev/urandom").and_then(|mut urandom| urandom.read_exact(&mut buf)).is_ok() {
+        return u64::from_ne_bytes(buf)
+    }
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    hasher.finish()
+}
@@ -0,0 +1,420 @@
+// Range header
+// https://tools.ietf.org/html/rfc2326#section-12.29
+//
+// Range            = "Range" ":" 1\#ranges-specifier
+//                    [ ";" "time" "=" utc-time ]
+// ranges-specifier = npt-range | utc-range | smpte-range
+//
+// Normal Play Time, https://tools.ietf.org/html/rfc2326#section-3.6
+//   npt-range    = ( npt-time "-" [ npt-time ] ) | ( "-" npt-time )
+//   npt-time     = "now" | npt-sec | npt-hhmmss
+//   npt-sec      = 1*DIGIT [ "." *DIGIT ]
+//   npt-hhmmss   = npt-hh ":" npt-mm ":" npt-ss [ "." *DIGIT ]
+//
+// SMPTE Relative Timestamps, https://tools.ietf.org/html/rfc2326#section-3.5
+//   smpte-range  = smpte-type "=" smpte-time "-" [ smpte-time ]
+//   smpte-type   = "smpte" | "smpte-30-drop" | "smpte-25"
+//   smpte-time   = 1*2DIGIT ":" 1*2DIGIT ":" 1*2DIGIT [ ":" 1*2DIGIT ]
+//                  [ "." 1*2DIGIT ]
+//
+// Absolute Time, https://tools.ietf.org/html/rfc2326#section-3.7
+//   utc-range    = "clock" "=" utc-time "-" [ utc-time ]
+//   utc-time     = utc-date "T" utc-time "Z"
+//   utc-date     = 8DIGIT                    ; < YYYYMMDD >
+//   utc-time     = 6DIGIT [ "." fraction ]   ; < HHMMSS.fraction >
+//
+// For example:
+//   Range: npt=10.5-
+//   Range: smpte-30-drop=10:07:33:15.2-
+//   Range: clock=19961108T142300Z-19961108T143520Z;time=19961108T142000Z
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+use error::{ Error, Result };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NptTime {
+    Now,
+    /// Seconds from the beginning of the presentation.
+    Seconds(f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmpteType {
+    /// `smpte`, 30 frames per second
+    Smpte30,
+    /// `smpte-30-drop`, 29.97 frames per second
+    Smpte30Drop,
+    /// `smpte-25`, 25 frames per second
+    Smpte25
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SmpteTime {
+    pub hours    : u8,
+    pub minutes  : u8,
+    pub seconds  : u8,
+    pub frames   : u8,
+    /// Hundredths of a frame.
+    pub subframes: u8
+}
+
+/// An absolute time in UTC, `YYYYMMDDTHHMMSS[.fraction]Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UtcTime {
+    pub year  : u16,
+    pub month : u8,
+    pub day   : u8,
+    pub hour  : u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanos : u32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeSpec {
+    /// `start` is `None` for the `-npt-time` form.
+    Npt { start: Option<NptTime>, end: Option<NptTime> },
+    Smpte { kind: SmpteType, start: SmpteTime, end: Option<SmpteTime> },
+    Clock { start: UtcTime, end: Option<UtcTime> }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub spec: RangeSpec,
+    /// When the request should take effect.
+    pub time: Option<UtcTime>
+}
+
+impl Range {
+    pub fn new (spec: RangeSpec) -> Range {
+        Range { spec, time: None }
+    }
+    /// `npt=<seconds>-`
+    pub fn npt_from(seconds: f64) -> Range {
+        Range::new(RangeSpec::Npt { start: Some(NptTime::Seconds(seconds)), end: None })
+    }
+    /// `npt=now-`, live playback.
+    pub fn now() -> Range {
+        Range::new(RangeSpec::Npt { start: Some(NptTime::Now), end: None })
+    }
+    /// `clock=<start>-[<end>]`, recorded playback.
+    pub fn clock(start: UtcTime, end: Option<UtcTime>) -> Range {
+        Range::new(RangeSpec::Clock { start, end })
+    }
+}
+
+impl UtcTime {
+    pub fn from_system_time(time: SystemTime) -> UtcTime {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let rem = secs % 86400;
+        UtcTime {
+            year: year as u16,
+            month,
+            day,
+            hour: (rem / 3600) as u8,
+            minute: (rem / 60 % 60) as u8,
+            second: (rem % 60) as u8,
+            nanos: since_epoch.subsec_nanos()
+        }
+    }
+    pub fn to_system_time(&self) -> SystemTime {
+        let days = days_from_civil(i64::from(self.year), self.month, self.day);
+        let secs = days * 86400 + i64::from(self.hour) * 3600
+                 + i64::from(self.minute) * 60 + i64::from(self.second);
+        UNIX_EPOCH + Duration::new(secs.max(0) as u64, self.nanos)
+    }
+}
+
+// Howard Hinnant's date algorithms, http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl FromStr for NptTime {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<NptTime> {
+        if s == "now" {
+            return Ok(NptTime::Now)
+        }
+        let parts = s.split(':').collect::<Vec<&str>>();
+        let seconds = match parts.len() {
+            1 => number::<f64>(parts[0])?,
+            3 => number::<u32>(parts[0])? as f64 * 3600.0
+               + number::<u32>(parts[1])? as f64 * 60.0
+               + number::<f64>(parts[2])?,
+            _ => return Err(Error::Header)
+        };
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(Error::Header)
+        }
+        Ok(NptTime::Seconds(seconds))
+    }
+}
+
+impl fmt::Display for NptTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NptTime::Now => f.write_str("now"),
+            NptTime::Seconds(seconds) => {
+                let s = format!("{:.3}", seconds);
+                f.write_str(s.trim_end_matches('0').trim_end_matches('.'))
+            }
+        }
+    }
+}
+
+impl FromStr for SmpteTime {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<SmpteTime> {
+        // Subframes are hundredths of a frame, so `.2` is 20 of them.
+        let (time, subframes) = match s.find('.') {
+            Some(i) => match s[i + 1..].len() {
+                1 => (&s[..i], number::<u8>(&s[i + 1..])? * 10),
+                2 => (&s[..i], number(&s[i + 1..])?),
+                _ => return Err(Error::Header)
+            },
+            None => (s, 0)
+        };
+        let parts = time.split(':').map(number::<u8>).collect::<Result<Vec<u8>>>()?;
+        if parts.len() != 3 && parts.len() != 4 {
+            return Err(Error::Header)
+        }
+        Ok(SmpteTime {
+            hours: parts[0],
+            minutes: parts[1],
+            seconds: parts[2],
+            frames: parts.get(3).cloned().unwrap_or(0),
+            subframes
+        })
+    }
+}
+
+impl fmt::Display for SmpteTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hours, self.minutes, self.seconds)?;
+        if self.frames != 0 || self.subframes != 0 {
+            write!(f, ":{:02}", self.frames)?;
+        }
+        if self.subframes != 0 {
+            let subframes = format!("{:02}", self.subframes);
+            write!(f, ".{}", subframes.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for UtcTime {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<UtcTime> {
+        let s = s.strip_suffix('Z').ok_or(Error::Header)?;
+        let t = s.find('T').ok_or(Error::Header)?;
+        let (date, time) = (&s[..t], &s[t + 1..]);
+        let (time, fraction) = match time.find('.') {
+            Some(i) => (&time[..i], &time[i + 1..]),
+            None => (time, "")
+        };
+        // Checking the digits first keeps the slices below on character
+        // boundaries.
+        if date.len() != 8 || time.len() != 6 || fraction.len() > 9
+            || ![date, time, fraction].iter().all(|s| s.bytes().all(|b| b.is_ascii_digit())) {
+            return Err(Error::Header)
+        }
+        let nanos = if fraction.is_empty() {
+            0
+        } else {
+            number::<u32>(fraction)? * 10u32.pow(9 - fraction.len() as u32)
+        };
+        let utc = UtcTime {
+            year: number(&date[..4])?,
+            month: number(&date[4..6])?,
+            day: number(&date[6..])?,
+            hour: number(&time[..2])?,
+            minute: number(&time[2..4])?,
+            second: number(&time[4..])?,
+            nanos
+        };
+        if utc.month == 0 || utc.month > 12 || utc.day == 0 || utc.day > 31
+            || utc.hour > 23 || utc.minute > 59 || utc.second > 60 {
+            return Err(Error::Header)
+        }
+        Ok(utc)
+    }
+}
+
+impl fmt::Display for UtcTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}{:02}{:02}T{:02}{:02}{:02}",
+               self.year, self.month, self.day, self.hour, self.minute, self.second)?;
+        if self.nanos != 0 {
+            let fraction = format!("{:09}", self.nanos);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        f.write_str("Z")
+    }
+}
+
+impl FromStr for RangeSpec {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<RangeSpec> {
+        let (unit, value) = match s.find('=') {
+            Some(i) => (s[..i].trim(), s[i + 1..].trim()),
+            None => ("npt", s.trim())
+        };
+        let dash = value.find('-').ok_or(Error::Header)?;
+        let (start, end) = (value[..dash].trim(), value[dash + 1..].trim());
+        match unit.to_ascii_lowercase().as_ref() {
+            "npt" => {
+                if start.is_empty() && end.is_empty() {
+                    return Err(Error::Header)
+                }
+                Ok(RangeSpec::Npt { start: optional(start)?, end: optional(end)? })
+            },
+            "clock" => Ok(RangeSpec::Clock { start: start.parse()?, end: optional(end)? }),
+            unit => {
+                let kind = match unit {
+                    "smpte"         => SmpteType::Smpte30,
+                    "smpte-30-drop" => SmpteType::Smpte30Drop,
+                    "smpte-25"      => SmpteType::Smpte25,
+                    _ => return Err(Error::Header)
+                };
+                Ok(RangeSpec::Smpte { kind, start: start.parse()?, end: optional(end)? })
+            }
+        }
+    }
+}
+
+impl fmt::Display for RangeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RangeSpec::Npt { start, end } => {
+                f.write_str("npt=")?;
+                write_range(f, start, end)
+            },
+            RangeSpec::Smpte { kind, start, end } => {
+                f.write_str(match kind {
+                    SmpteType::Smpte30     => "smpte=",
+                    SmpteType::Smpte30Drop => "smpte-30-drop=",
+                    SmpteType::Smpte25     => "smpte-25="
+                })?;
+                write_range(f, Some(start), end)
+            },
+            RangeSpec::Clock { start, end } => {
+                f.write_str("clock=")?;
+                write_range(f, Some(start), end)
+            }
+        }
+    }
+}
+
+impl FromStr for Range {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Range> {
+        let mut params = s.split(';').map(str::trim);
+        let mut range = Range::new(params.next().unwrap_or("").parse()?);
+        for param in params {
+            let mut kv = param.splitn(2, '=').map(str::trim);
+            if kv.next().unwrap_or("").eq_ignore_ascii_case("time") {
+                range.time = Some(kv.next().unwrap_or("").parse()?);
+            }
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.spec)?;
+        if let Some(time) = self.time {
+            write!(f, ";time={}", time)?;
+        }
+        Ok(())
+    }
+}
+
+fn number<T: FromStr>(s: &str) -> Result<T> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return Err(Error::Header)
+    }
+    s.parse().map_err(|_| Error::Header)
+}
+
+fn optional<T: FromStr<Err = Error>>(s: &str) -> Result<Option<T>> {
+    if s.is_empty() { Ok(None) } else { s.parse().map(Some) }
+}
+
+fn write_range<T: fmt::Display>(f: &mut fmt::Formatter, start: Option<T>, end: Option<T>) -> fmt::Result {
+    if let Some(start) = start {
+        write!(f, "{}", start)?;
+    }
+    f.write_str("-")?;
+    if let Some(end) = end {
+        write!(f, "{}", end)?;
+    }
+    Ok(())
+}
+
+
+
+#[test]
+fn test() {
+    let range: Range = "npt=10.5-".parse().unwrap();
+    assert_eq!(range, Range::npt_from(10.5));
+    assert_eq!(range.to_string(), "npt=10.5-");
+    assert_eq!("npt=now-".parse::<Range>().unwrap(), Range::now());
+    assert_eq!("npt=0.000-".parse::<Range>().unwrap().to_string(), "npt=0-");
+    assert_eq!("npt=-20".parse::<Range>().unwrap().to_string(), "npt=-20");
+    assert_eq!("npt=00:01:02.5-1:00:00".parse::<Range>().unwrap().spec,
+               RangeSpec::Npt { start: Some(NptTime::Seconds(62.5)), end: Some(NptTime::Seconds(3600.0)) });
+
+    let range: Range = "smpte-30-drop=10:07:33:15.2-".parse().unwrap();
+    let start = SmpteTime { hours: 10, minutes: 7, seconds: 33, frames: 15, subframes: 20 };
+    assert_eq!(range.spec, RangeSpec::Smpte { kind: SmpteType::Smpte30Drop, start, end: None });
+    assert_eq!(range.to_string(), "smpte-30-drop=10:07:33:15.2-");
+    assert_eq!("smpte=10:07:33:15.02-".parse::<Range>().unwrap().to_string(), "smpte=10:07:33:15.02-");
+    assert!("smpte=10:07:33:15.123-".parse::<Range>().is_err());
+    assert_eq!("smpte=10:07:00-10:07:33".parse::<Range>().unwrap().to_string(), "smpte=10:07:00-10:07:33");
+
+    let text = "clock=20261018T120000Z-20261018T123000.25Z;time=20261018T115959Z";
+    let range: Range = text.parse().unwrap();
+    assert_eq!(range.to_string(), text);
+    match range.spec {
+        RangeSpec::Clock { start, end: Some(end) } => {
+            assert_eq!((start.year, start.month, start.day, start.hour), (2026, 10, 18, 12));
+            assert_eq!(end.nanos, 250_000_000);
+            assert_eq!(UtcTime::from_system_time(end.to_system_time()), end);
+            assert_eq!(start.to_system_time().duration_since(UNIX_EPOCH).unwrap().as_secs(), 1792324800);
+        },
+        ref other => panic!("{:?}", other)
+    }
+
+    assert!("npt=-".parse::<Range>().is_err());
+    assert!("npt=abc-".parse::<Range>().is_err());
+    assert!("clock=20261018-".parse::<Range>().is_err());
+    assert!("clock=202é118T120000Z-".parse::<Range>().is_err());
+    assert!("clock=20260118T1é000Z-".parse::<Range>().is_err());
+    assert!("frames=1-2".parse::<Range>().is_err());
+}
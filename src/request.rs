@@ -1,16 +1,204 @@
-// extern crate method;
-// extern crate status;
-
+use std::str::FromStr;
+use std::io::Write;
 
 use method::Method;
-use status::StatusCode;
 use version::RtspVersion;
 use header::Headers;
+use error::{ Error, Result };
+use parse;
+use codec;
+
+// https://tools.ietf.org/html/rfc2326#section-6
+//
+// Request      =       Request-Line          ; Section 6.1
+//                      *(      general-header     ; Section 5
+//                      |       request-header     ; Section 6.2
+//                      |       entity-header )    ; Section 8.1
+//                      CRLF
+//                      [ message-body ]          ; Section 4.3
+//
+// Request-Line = Method SP Request-URI SP RTSP-Version CRLF
 
-#[derive(Debug)]
-pub struct Response {
+#[derive(Debug, Clone)]
+pub struct Request {
     method : Method,
-    status : StatusCode,
+    uri    : String,
     version: RtspVersion,
-    headers: Headers
-}
\ No newline at end of file
+    headers: Headers,
+    body   : Vec<u8>,
+    // Set by the server, never sent.
+    user   : Option<String>
+}
+
+impl Request {
+    pub fn new (method: Method, uri: &str) -> Request {
+        Request {
+            method,
+            uri: uri.to_string(),
+            version: RtspVersion::Rtsp10,
+            headers: Headers::new(),
+            body: Vec::new(),
+            user: None
+        }
+    }
+    /// Parses one request from the front of `buf`.
+    ///
+    /// On success the request is returned together with the number of bytes
+    /// it occupied, so that pipelined messages can be read from the rest of
+    /// the buffer. `Error::Incomplete` means more bytes are needed.
+    pub fn parse(buf: &[u8]) -> Result<(Request, usize)> {
+        let head = parse::head(buf)?;
+
+        let mut parts = head.start_line.split(' ').filter(|part| !part.is_empty());
+        let method = match parts.next() {
+            Some(method) if parse::is_token(method) => Method::from_str(method)?,
+            _ => return Err(Error::Method)
+        };
+        let uri = parts.next()
+                       .ok_or_else(|| Error::Uri("Missing Request-URI".to_string()))?;
+        let version = RtspVersion::from_str(parts.next().unwrap_or(""))?;
+        if parts.next().is_some() {
+            return Err(Error::Version)
+        }
+
+        let (body, len) = head.body(buf)?;
+        Ok((Request {
+            method,
+            uri: uri.to_string(),
+            version,
+            headers: head.headers,
+            body: body.to_vec(),
+            user: None
+        }, len))
+    }
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+    pub fn version(&self) -> RtspVersion {
+        self.version
+    }
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+    pub fn set_body(&mut self, body: Vec<u8>) {
+        self.body = body;
+    }
+    /// The user a server authenticated the request as, `None` when it does
+    /// not ask for credentials.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+    pub fn set_user(&mut self, user: Option<String>) {
+        self.user = user;
+    }
+    /// The `CSeq` of the request, if present and numeric.
+    pub fn cseq(&self) -> Option<u32> {
+        self.headers.get("CSeq").and_then(|cseq| cseq.trim().parse().ok())
+    }
+    pub fn set_cseq(&mut self, cseq: u32) {
+        self.headers.insert("CSeq".to_string(), cseq.to_string());
+    }
+    /// Appends the wire form of the request to `buf`.
+    ///
+    /// `CSeq` goes right after the start line and `Content-Length` is
+    /// always derived from the body.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        self.write_to(buf).expect("writing to a Vec cannot fail")
+    }
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        codec::write_message(w, format_args!("{} {} {}", self.method, self.uri, self.version),
+                             self.cseq(), &self.headers, &self.body)?;
+        Ok(())
+    }
+}
+
+impl FromStr for Request {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Request> {
+        Request::parse(s.as_bytes()).map(|(request, _)| request)
+    }
+}
+
+
+
+#[test]
+fn test() {
+    let buf = b"ANNOUNCE rtsp://example.com/live RTSP/1.0\r\n\
+                CSeq: 7\r\n\
+                Content-Type: application/sdp\r\n\
+                Content-Length: 4\r\n\
+                \r\n\
+                v=0\nOPTIONS * RTSP/1.0\r\n";
+    let (request, len) = Request::parse(buf).unwrap();
+    assert_eq!(request.method(), &Method::Announce);
+    assert_eq!(request.uri(), "rtsp://example.com/live");
+    assert_eq!(request.version(), RtspVersion::Rtsp10);
+    assert_eq!(request.cseq(), Some(7));
+    assert_eq!(request.headers().get("content-type"), Some(&"application/sdp".to_string()));
+    assert_eq!(request.body(), b"v=0\n");
+    assert_eq!(&buf[len..], b"OPTIONS * RTSP/1.0\r\n");
+
+    // Bare LF line endings and folded header values.
+    let request: Request = "GET_PARAMETER * RTSP/1.0\nCSeq: 2\nX-Foo: a\n b\n\n".parse().unwrap();
+    assert_eq!(request.method(), &Method::GetParameter);
+    assert_eq!(request.headers().get("X-Foo"), Some(&"a b".to_string()));
+
+    match Request::parse(&buf[..60]) {
+        Err(Error::Incomplete) => (),
+        other => panic!("{:?}", other)
+    }
+    match Request::parse(&buf[..buf.len() - 22]) {
+        Err(Error::Incomplete) => (),
+        other => panic!("{:?}", other)
+    }
+    match "PLAY rtsp://a/ HTTP/1.1\r\n\r\n".parse::<Request>() {
+        Err(Error::Version) => (),
+        other => panic!("{:?}", other)
+    }
+    match "PL{AY rtsp://a/ RTSP/1.0\r\n\r\n".parse::<Request>() {
+        Err(Error::Method) => (),
+        other => panic!("{:?}", other)
+    }
+    match "PLAY rtsp://a/ RTSP/1.0\r\nCSeq 1\r\n\r\n".parse::<Request>() {
+        Err(Error::Header) => (),
+        other => panic!("{:?}", other)
+    }
+    match Request::parse(&vec![b'A'; parse::MAX_HEAD_SIZE + 1]) {
+        Err(Error::TooLarge) => (),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn round_trip() {
+    let methods = vec![Method::Options, Method::Describe, Method::Setup, Method::Play,
+                       Method::Pause, Method::Record, Method::Announce, Method::Teardown,
+                       Method::GetParameter, Method::SetParameter, Method::Redirect,
+                       Method::Extension("PLAY_NOTIFY".to_string())];
+    for (cseq, method) in methods.into_iter().enumerate() {
+        let mut request = Request::new(method.clone(), "rtsp://example.com/live");
+        request.set_cseq(cseq as u32);
+        request.headers_mut().insert("Content-Length".to_string(), "99".to_string());
+        request.set_body(b"position".to_vec());
+
+        let mut buf = Vec::new();
+        request.encode(&mut buf);
+        assert!(buf.starts_with(format!("{} rtsp://example.com/live RTSP/1.0\r\nCSeq: {}\r\n", method, cseq).as_bytes()));
+        let (parsed, len) = Request::parse(&buf).unwrap();
+        assert_eq!(len, buf.len());
+        assert_eq!(parsed.method(), &method);
+        assert_eq!(parsed.uri(), request.uri());
+        assert_eq!(parsed.cseq(), Some(cseq as u32));
+        assert_eq!(parsed.headers().get("Content-Length"), Some(&"8".to_string()));
+        assert_eq!(parsed.body(), b"position");
+    }
+}
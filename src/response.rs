@@ -1,25 +1,199 @@
+use std::str::FromStr;
+use std::io::Write;
 
-use method::Method;
 use status::StatusCode;
 use version::RtspVersion;
 use header::Headers;
+use error::{ Error, Result };
+use parse;
+use codec;
 
+// https://tools.ietf.org/html/rfc2326#section-7
+//
+// Response    =     Status-Line         ; Section 7.1
+//                   *(    general-header     ; Section 5
+//                   |     response-header    ; Section 7.1.2
+//                   |     entity-header )    ; Section 8.1
+//                   CRLF
+//                   [ message-body ]         ; Section 4.3
+//
+// Status-Line =   RTSP-Version SP Status-Code SP Reason-Phrase CRLF
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Response {
-    method : Method,
     status : StatusCode,
+    reason : Option<String>,
     version: RtspVersion,
-    headers: Headers
+    headers: Headers,
+    body   : Vec<u8>
 }
 
 impl Response {
     pub fn new () -> Response {
+        Response::with_status(StatusCode::Ok)
+    }
+    pub fn with_status(status: StatusCode) -> Response {
         Response {
-            method: Method::Options,
-            status: StatusCode::Ok,
+            status,
+            reason: None,
             version: RtspVersion::Rtsp10,
-            headers: Headers::new()
+            headers: Headers::new(),
+            body: Vec::new()
+        }
+    }
+    /// Parses one response from the front of `buf`.
+    ///
+    /// On success the response is returned together with the number of bytes
+    /// it occupied. `Error::Incomplete` means more bytes are needed.
+    pub fn parse(buf: &[u8]) -> Result<(Response, usize)> {
+        let head = parse::head(buf)?;
+
+        let mut parts = head.start_line.splitn(3, ' ');
+        let version = RtspVersion::from_str(parts.next().unwrap_or(""))?;
+        let code = parts.next().unwrap_or("");
+        if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::Status)
         }
+        let status = StatusCode::from_u16(code.parse().map_err(|_| Error::Status)?);
+        let reason = parts.next().unwrap_or("").trim();
+
+        let (body, len) = head.body(buf)?;
+        Ok((Response {
+            status,
+            reason: if reason.is_empty() { None } else { Some(reason.to_string()) },
+            version,
+            headers: head.headers,
+            body: body.to_vec()
+        }, len))
+    }
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+    /// The Reason-Phrase as received, falling back to the canonical one.
+    pub fn reason(&self) -> &str {
+        match self.reason {
+            Some(ref reason) => reason,
+            None => self.status.canonical_reason().unwrap_or("")
+        }
+    }
+    pub fn set_reason(&mut self, reason: &str) {
+        self.reason = Some(reason.to_string());
+    }
+    pub fn version(&self) -> RtspVersion {
+        self.version
+    }
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+    pub fn set_body(&mut self, body: Vec<u8>) {
+        self.body = body;
+    }
+    /// The `CSeq` of the response, if present and numeric.
+    pub fn cseq(&self) -> Option<u32> {
+        self.headers.get("CSeq").and_then(|cseq| cseq.trim().parse().ok())
+    }
+    pub fn set_cseq(&mut self, cseq: u32) {
+        self.headers.insert("CSeq".to_string(), cseq.to_string());
+    }
+    /// Appends the wire form of the response to `buf`.
+    ///
+    /// `CSeq` goes right after the start line and `Content-Length` is
+    /// always derived from the body.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        self.write_to(buf).expect("writing to a Vec cannot fail")
+    }
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        codec::write_message(w, format_args!("{} {} {}", self.version, self.status.to_u16(), self.reason()),
+                             self.cseq(), &self.headers, &self.body)?;
+        Ok(())
+    }
+}
+
+impl Default for Response {
+    fn default() -> Response {
+        Response::new()
     }
-}
\ No newline at end of file
+}
+
+impl FromStr for Response {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Response> {
+        Response::parse(s.as_bytes()).map(|(response, _)| response)
+    }
+}
+
+
+
+#[test]
+fn test() {
+    let buf = b"RTSP/1.0 200 OK\r\n\
+                CSeq: 2\r\n\
+                Content-Base: rtsp://example.com/live/\r\n\
+                Content-Length: 5\r\n\
+                \r\n\
+                v=0\r\n";
+    let (response, len) = Response::parse(buf).unwrap();
+    assert_eq!(len, buf.len());
+    assert_eq!(response.status(), StatusCode::Ok);
+    assert_eq!(response.reason(), "OK");
+    assert_eq!(response.cseq(), Some(2));
+    assert_eq!(response.headers().get("Content-Base"), Some(&"rtsp://example.com/live/".to_string()));
+    assert_eq!(response.body(), b"v=0\r\n");
+
+    let response: Response = "RTSP/1.0 299 Vendor Specific Thing\r\nCSeq: 3\r\n\r\n".parse().unwrap();
+    assert_eq!(response.status(), StatusCode::Extension(299));
+    assert_eq!(response.reason(), "Vendor Specific Thing");
+
+    let response: Response = "RTSP/1.0 401 Unauthorized\r\ncseq: 4\r\n\
+                              WWW-Authenticate: Digest realm=\"cam\"\r\n\
+                              WWW-Authenticate: Basic realm=\"cam\"\r\n\r\n".parse().unwrap();
+    assert_eq!(response.headers().keys(), vec!["cseq", "WWW-Authenticate", "WWW-Authenticate"]);
+    assert_eq!(response.headers().get_all("www-authenticate").len(), 2);
+
+    let response: Response = "RTSP/1.0 455\r\n\r\n".parse().unwrap();
+    assert_eq!(response.status(), StatusCode::MethodNotValidInThisState);
+    assert_eq!(response.reason(), "Method Not Valid in This State");
+
+    match Response::parse(&buf[..buf.len() - 1]) {
+        Err(Error::Incomplete) => (),
+        other => panic!("{:?}", other)
+    }
+    match "RTSP/1.0 2x0 OK\r\n\r\n".parse::<Response>() {
+        Err(Error::Status) => (),
+        other => panic!("{:?}", other)
+    }
+    match "HTTP/1.1 200 OK\r\n\r\n".parse::<Response>() {
+        Err(Error::Version) => (),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn round_trip() {
+    for code in 100..600 {
+        let mut response = Response::with_status(StatusCode::from_u16(code));
+        if let StatusCode::Extension(..) = response.status() {
+            response.set_reason("Vendor Reason");
+        }
+        response.set_cseq(u32::from(code));
+        response.headers_mut().insert("Session".to_string(), "12345678".to_string());
+        response.set_body(b"body".to_vec());
+
+        let mut buf = Vec::new();
+        response.encode(&mut buf);
+        let (parsed, len) = Response::parse(&buf).unwrap();
+        assert_eq!(len, buf.len());
+        assert_eq!(parsed.status(), response.status());
+        assert_eq!(parsed.reason(), response.reason());
+        assert_eq!(parsed.cseq(), Some(u32::from(code)));
+        assert_eq!(parsed.headers().get("Session"), response.headers().get("Session"));
+        assert_eq!(parsed.headers().get("Content-Length"), Some(&"4".to_string()));
+        assert_eq!(parsed.body(), b"body");
+    }
+}
@@ -0,0 +1,765 @@
+// RTCP: RTP Control Protocol
+// https://tools.ietf.org/html/rfc3550#section-6
+//
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |V=2|P|   RC    |      PT       |             length            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Packets are sent stacked in compound packets, the first one a sender
+// or receiver report. Feedback messages come from
+// https://tools.ietf.org/html/rfc4585#section-6 (NACK, PLI),
+// https://tools.ietf.org/html/rfc5104#section-4.3.1 (FIR) and
+// https://tools.ietf.org/html/draft-alvestrand-rmcat-remb-03 (REMB).
+
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+
+use rtp;
+use error::{ Error, Result };
+
+pub const SENDER_REPORT: u8 = 200;
+pub const RECEIVER_REPORT: u8 = 201;
+pub const SOURCE_DESCRIPTION: u8 = 202;
+pub const BYE: u8 = 203;
+pub const APP: u8 = 204;
+pub const RTPFB: u8 = 205;
+pub const PSFB: u8 = 206;
+
+// SDES item types
+pub const CNAME: u8 = 1;
+pub const NAME: u8 = 2;
+pub const EMAIL: u8 = 3;
+pub const PHONE: u8 = 4;
+pub const LOC: u8 = 5;
+pub const TOOL: u8 = 6;
+pub const NOTE: u8 = 7;
+pub const PRIV: u8 = 8;
+
+/// Seconds from the NTP epoch, 1900, to the Unix one.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReportBlock {
+    pub ssrc               : u32,
+    pub fraction_lost      : u8,
+    /// 24 bits, negative when duplicates outnumber losses.
+    pub cumulative_lost    : i32,
+    pub highest_sequence   : u32,
+    pub jitter             : u32,
+    /// The middle 32 bits of the NTP timestamp of the last SR.
+    pub last_sr            : u32,
+    /// In units of 1/65536 seconds.
+    pub delay_since_last_sr: u32
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderReport {
+    pub ssrc         : u32,
+    pub ntp_timestamp: u64,
+    pub rtp_timestamp: u32,
+    pub packet_count : u32,
+    pub octet_count  : u32,
+    pub reports      : Vec<ReportBlock>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiverReport {
+    pub ssrc   : u32,
+    pub reports: Vec<ReportBlock>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesItem {
+    /// `CNAME`, `NAME`, ...
+    pub kind: u8,
+    pub text: String
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesChunk {
+    pub ssrc : u32,
+    pub items: Vec<SdesItem>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bye {
+    pub ssrcs : Vec<u32>,
+    pub reason: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct App {
+    pub subtype: u8,
+    pub ssrc   : u32,
+    pub name   : [u8; 4],
+    pub data   : Vec<u8>
+}
+
+/// Generic NACK, RTPFB FMT 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nack {
+    pub sender_ssrc: u32,
+    pub media_ssrc : u32,
+    /// The sequence numbers asked for again.
+    pub lost       : Vec<u16>
+}
+
+/// Picture Loss Indication, PSFB FMT 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pli {
+    pub sender_ssrc: u32,
+    pub media_ssrc : u32
+}
+
+/// Full Intra Request, PSFB FMT 4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fir {
+    pub sender_ssrc: u32,
+    /// The SSRC asked for a key frame and the command sequence number.
+    pub entries    : Vec<(u32, u8)>
+}
+
+/// Receiver Estimated Maximum Bitrate, PSFB FMT 15 with `REMB`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remb {
+    pub sender_ssrc: u32,
+    /// Bits per second.
+    pub bitrate    : u64,
+    pub ssrcs      : Vec<u32>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    SenderReport(SenderReport),
+    ReceiverReport(ReceiverReport),
+    SourceDescription(Vec<SdesChunk>),
+    Bye(Bye),
+    App(App),
+    Nack(Nack),
+    Pli(Pli),
+    Fir(Fir),
+    Remb(Remb),
+    /// Any other packet type or feedback format, without its header.
+    Unknown { packet_type: u8, count: u8, data: Vec<u8> }
+}
+
+/// Iterates over the packets of a compound packet.
+#[derive(Debug, Clone)]
+pub struct Packets<'a> {
+    buf: &'a [u8]
+}
+
+impl SenderReport {
+    /// The sender's wallclock time when the report was sent.
+    pub fn wallclock(&self) -> SystemTime {
+        ntp_to_system_time(self.ntp_timestamp)
+    }
+    /// The wallclock time of a packet from this sender with RTP timestamp
+    /// `rtp_timestamp`, for a clock running at `clock_rate` Hz.
+    ///
+    /// Timestamps up to half the RTP clock range away from the report's
+    /// are placed on the near side of it, handling wrap-around.
+    pub fn wallclock_at(&self, rtp_timestamp: u32, clock_rate: u32) -> SystemTime {
+        let delta = rtp_timestamp.wrapping_sub(self.rtp_timestamp) as i32;
+        let nanos = (i64::from(delta).unsigned_abs() as u128 * 1_000_000_000 / u128::from(clock_rate.max(1))) as u64;
+        if delta >= 0 {
+            self.wallclock() + Duration::from_nanos(nanos)
+        } else {
+            self.wallclock() - Duration::from_nanos(nanos)
+        }
+    }
+}
+
+impl SdesChunk {
+    pub fn cname(ssrc: u32, cname: &str) -> SdesChunk {
+        SdesChunk { ssrc, items: vec![SdesItem { kind: CNAME, text: cname.to_string() }] }
+    }
+}
+
+impl Packet {
+    /// Parses the packet at the front of `buf`, returning it together with
+    /// the number of bytes it took.
+    pub fn parse(buf: &[u8]) -> Result<(Packet, usize)> {
+        if buf.len() < 4 {
+            return Err(invalid("Packet is shorter than the RTCP header"))
+        }
+        if buf[0] >> 6 != rtp::VERSION {
+            return Err(invalid("RTCP version is not 2"))
+        }
+        let len = 4 * (u16::from_be_bytes([buf[2], buf[3]]) as usize + 1);
+        if buf.len() < len {
+            return Err(invalid("RTCP length exceeds the packet"))
+        }
+        let count = buf[0] & 0x1f;
+        let packet_type = buf[1];
+        let mut body = &buf[4..len];
+        if buf[0] & 0x20 != 0 {
+            let padding = buf[len - 1] as usize;
+            if padding == 0 || padding > body.len() {
+                return Err(invalid("Invalid RTCP padding"))
+            }
+            body = &body[..body.len() - padding];
+        }
+        let mut r = Reader { buf: body };
+        let packet = match packet_type {
+            SENDER_REPORT => Packet::SenderReport(SenderReport {
+                ssrc: r.u32()?,
+                ntp_timestamp: u64::from(r.u32()?) << 32 | u64::from(r.u32()?),
+                rtp_timestamp: r.u32()?,
+                packet_count: r.u32()?,
+                octet_count: r.u32()?,
+                reports: report_blocks(&mut r, count)?
+            }),
+            RECEIVER_REPORT => Packet::ReceiverReport(ReceiverReport {
+                ssrc: r.u32()?,
+                reports: report_blocks(&mut r, count)?
+            }),
+            SOURCE_DESCRIPTION => {
+                let mut chunks = Vec::new();
+                for _ in 0..count {
+                    chunks.push(sdes_chunk(&mut r)?);
+                }
+                Packet::SourceDescription(chunks)
+            },
+            BYE => {
+                let mut ssrcs = Vec::new();
+                for _ in 0..count {
+                    ssrcs.push(r.u32()?);
+                }
+                let reason = match r.buf.first() {
+                    Some(&len) => Some(String::from_utf8_lossy(r.take(1 + len as usize)?.get(1..).unwrap_or(&[])).into_owned()),
+                    None => None
+                };
+                Packet::Bye(Bye { ssrcs, reason })
+            },
+            APP => {
+                let ssrc = r.u32()?;
+                let name = r.take(4)?;
+                Packet::App(App { subtype: count, ssrc, name: [name[0], name[1], name[2], name[3]], data: r.buf.to_vec() })
+            },
+            RTPFB if count == 1 => {
+                let (sender_ssrc, media_ssrc) = (r.u32()?, r.u32()?);
+                let mut lost = Vec::new();
+                while !r.buf.is_empty() {
+                    let pid = r.u16()?;
+                    let blp = r.u16()?;
+                    lost.push(pid);
+                    lost.extend((0..16).filter(|i| blp & (1 << i) != 0).map(|i| pid.wrapping_add(i + 1)));
+                }
+                Packet::Nack(Nack { sender_ssrc, media_ssrc, lost })
+            },
+            PSFB if count == 1 => Packet::Pli(Pli { sender_ssrc: r.u32()?, media_ssrc: r.u32()? }),
+            PSFB if count == 4 => {
+                let sender_ssrc = r.u32()?;
+                r.u32()?;
+                let mut entries = Vec::new();
+                while !r.buf.is_empty() {
+                    let ssrc = r.u32()?;
+                    let seq = r.take(4)?[0];
+                    entries.push((ssrc, seq));
+                }
+                Packet::Fir(Fir { sender_ssrc, entries })
+            },
+            PSFB if count == 15 && body.len() >= 12 && &body[8..12] == b"REMB" => {
+                let sender_ssrc = r.u32()?;
+                r.take(8)?;
+                let num = r.take(1)?[0];
+                let bits = r.take(3)?;
+                let exp = bits[0] >> 2;
+                let mantissa = u64::from(bits[0] & 0x03) << 16 | u64::from(bits[1]) << 8 | u64::from(bits[2]);
+                let mut ssrcs = Vec::new();
+                for _ in 0..num {
+                    ssrcs.push(r.u32()?);
+                }
+                Packet::Remb(Remb { sender_ssrc, bitrate: mantissa.checked_shl(u32::from(exp)).unwrap_or(u64::MAX), ssrcs })
+            },
+            _ => Packet::Unknown { packet_type, count, data: body.to_vec() }
+        };
+        Ok((packet, len))
+    }
+    /// The RTCP packet type.
+    pub fn packet_type(&self) -> u8 {
+        match *self {
+            Packet::SenderReport(_) => SENDER_REPORT,
+            Packet::ReceiverReport(_) => RECEIVER_REPORT,
+            Packet::SourceDescription(_) => SOURCE_DESCRIPTION,
+            Packet::Bye(_) => BYE,
+            Packet::App(_) => APP,
+            Packet::Nack(_) => RTPFB,
+            Packet::Pli(_) | Packet::Fir(_) | Packet::Remb(_) => PSFB,
+            Packet::Unknown { packet_type, .. } => packet_type
+        }
+    }
+    /// Appends the packet to `buf`, which is left as it was on error.
+    pub fn encode(&self, buf: &mut Vec<u8>) -> Result<()> {
+        let start = buf.len();
+        let result = self.write(buf, start);
+        if result.is_err() {
+            buf.truncate(start);
+        }
+        result
+    }
+    fn write(&self, buf: &mut Vec<u8>, start: usize) -> Result<()> {
+        buf.extend_from_slice(&[0, self.packet_type(), 0, 0]);
+        let count = match *self {
+            Packet::SenderReport(ref sr) => {
+                put_u32(buf, sr.ssrc);
+                buf.extend_from_slice(&sr.ntp_timestamp.to_be_bytes());
+                put_u32(buf, sr.rtp_timestamp);
+                put_u32(buf, sr.packet_count);
+                put_u32(buf, sr.octet_count);
+                put_report_blocks(buf, &sr.reports)?
+            },
+            Packet::ReceiverReport(ref rr) => {
+                put_u32(buf, rr.ssrc);
+                put_report_blocks(buf, &rr.reports)?
+            },
+            Packet::SourceDescription(ref chunks) => {
+                for chunk in chunks {
+                    put_u32(buf, chunk.ssrc);
+                    for item in &chunk.items {
+                        if item.text.len() > 255 {
+                            return Err(invalid("SDES item is longer than 255 bytes"))
+                        }
+                        buf.push(item.kind);
+                        buf.push(item.text.len() as u8);
+                        buf.extend_from_slice(item.text.as_bytes());
+                    }
+                    // The null item ending the chunk, padded to 32 bits.
+                    buf.push(0);
+                    pad(buf, start);
+                }
+                chunks.len()
+            },
+            Packet::Bye(ref bye) => {
+                for ssrc in &bye.ssrcs {
+                    put_u32(buf, *ssrc);
+                }
+                if let Some(ref reason) = bye.reason {
+                    if reason.len() > 255 {
+                        return Err(invalid("BYE reason is longer than 255 bytes"))
+                    }
+                    buf.push(reason.len() as u8);
+                    buf.extend_from_slice(reason.as_bytes());
+                    pad(buf, start);
+                }
+                bye.ssrcs.len()
+            },
+            Packet::App(ref app) => {
+                if app.data.len() % 4 != 0 {
+                    return Err(invalid("APP data is not a multiple of 32 bits"))
+                }
+                put_u32(buf, app.ssrc);
+                buf.extend_from_slice(&app.name);
+                buf.extend_from_slice(&app.data);
+                app.subtype as usize
+            },
+            Packet::Nack(ref nack) => {
+                put_u32(buf, nack.sender_ssrc);
+                put_u32(buf, nack.media_ssrc);
+                let mut lost = nack.lost.clone();
+                lost.sort_unstable();
+                lost.dedup();
+                let mut i = 0;
+                while i < lost.len() {
+                    let pid = lost[i];
+                    let mut blp = 0u16;
+                    i += 1;
+                    while i < lost.len() && lost[i].wrapping_sub(pid) <= 16 {
+                        blp |= 1 << (lost[i].wrapping_sub(pid) - 1);
+                        i += 1;
+                    }
+                    buf.extend_from_slice(&pid.to_be_bytes());
+                    buf.extend_from_slice(&blp.to_be_bytes());
+                }
+                1
+            },
+            Packet::Pli(ref pli) => {
+                put_u32(buf, pli.sender_ssrc);
+                put_u32(buf, pli.media_ssrc);
+                1
+            },
+            Packet::Fir(ref fir) => {
+                put_u32(buf, fir.sender_ssrc);
+                put_u32(buf, 0);
+                for (ssrc, seq) in &fir.entries {
+                    put_u32(buf, *ssrc);
+                    buf.extend_from_slice(&[*seq, 0, 0, 0]);
+                }
+                4
+            },
+            Packet::Remb(ref remb) => {
+                if remb.ssrcs.len() > 255 {
+                    return Err(invalid("More than 255 SSRCs in REMB"))
+                }
+                put_u32(buf, remb.sender_ssrc);
+                put_u32(buf, 0);
+                buf.extend_from_slice(b"REMB");
+                let mut exp = 0;
+                let mut mantissa = remb.bitrate;
+                while mantissa >= 1 << 18 {
+                    mantissa >>= 1;
+                    exp += 1;
+                }
+                buf.push(remb.ssrcs.len() as u8);
+                buf.push((exp << 2) as u8 | (mantissa >> 16) as u8);
+                buf.push((mantissa >> 8) as u8);
+                buf.push(mantissa as u8);
+                for ssrc in &remb.ssrcs {
+                    put_u32(buf, *ssrc);
+                }
+                15
+            },
+            Packet::Unknown { count, ref data, .. } => {
+                if data.len() % 4 != 0 {
+                    return Err(invalid("RTCP packet is not a multiple of 32 bits"))
+                }
+                buf.extend_from_slice(data);
+                count as usize
+            }
+        };
+        if count > 31 {
+            return Err(invalid("More than 31 items in an RTCP packet"))
+        }
+        let words = (buf.len() - start) / 4 - 1;
+        if words > 0xffff {
+            return Err(invalid("RTCP packet is too large"))
+        }
+        buf[start] = rtp::VERSION << 6 | count as u8;
+        buf[start + 2..start + 4].copy_from_slice(&(words as u16).to_be_bytes());
+        Ok(())
+    }
+}
+
+impl<'a> Packets<'a> {
+    pub fn new (buf: &'a [u8]) -> Packets<'a> {
+        Packets { buf }
+    }
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = Result<Packet>;
+    /// Ends after the first error, the rest of the buffer cannot be framed.
+    fn next(&mut self) -> Option<Result<Packet>> {
+        if self.buf.is_empty() {
+            return None
+        }
+        match Packet::parse(self.buf) {
+            Ok((packet, len)) => {
+                self.buf = &self.buf[len..];
+                Some(Ok(packet))
+            },
+            Err(e) => {
+                self.buf = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Parses a compound packet, checking that it starts with a report.
+///
+/// Reduced-size RTCP, https://tools.ietf.org/html/rfc5506, may send
+/// feedback on its own; read such packets with `Packets`.
+pub fn parse_compound(buf: &[u8]) -> Result<Vec<Packet>> {
+    let packets = Packets::new(buf).collect::<Result<Vec<Packet>>>()?;
+    match packets.first() {
+        Some(&Packet::SenderReport(_)) | Some(&Packet::ReceiverReport(_)) => Ok(packets),
+        Some(_) => Err(invalid("Compound packet does not start with a report")),
+        None => Err(invalid("Empty compound packet"))
+    }
+}
+
+/// Writes `packets` one after the other as a compound packet. On error
+/// `buf` is left as it was, without the packets before the failing one.
+pub fn encode_compound(packets: &[Packet], buf: &mut Vec<u8>) -> Result<()> {
+    let start = buf.len();
+    for packet in packets {
+        if let Err(e) = packet.encode(buf) {
+            buf.truncate(start);
+            return Err(e)
+        }
+    }
+    Ok(())
+}
+
+pub fn ntp_to_system_time(ntp: u64) -> SystemTime {
+    let seconds = ntp >> 32;
+    let nanos = ((ntp & 0xffff_ffff) * 1_000_000_000) >> 32;
+    if seconds >= NTP_UNIX_OFFSET {
+        UNIX_EPOCH + Duration::new(seconds - NTP_UNIX_OFFSET, nanos as u32)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(NTP_UNIX_OFFSET - seconds) + Duration::from_nanos(nanos)
+    }
+}
+
+pub fn system_time_to_ntp(time: SystemTime) -> u64 {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let fraction = (u64::from(since.subsec_nanos()) << 32) / 1_000_000_000;
+    (since.as_secs() + NTP_UNIX_OFFSET) << 32 | fraction
+}
+
+/// Reception statistics for one source, for the report blocks of RRs.
+/// https://tools.ietf.org/html/rfc3550#appendix-A.3
+#[derive(Debug, Clone)]
+pub struct ReceptionStats {
+    ssrc          : u32,
+    clock_rate    : u32,
+    base_seq      : u32,
+    max_seq       : u16,
+    cycles        : u32,
+    received      : u32,
+    expected_prior: u32,
+    received_prior: u32,
+    // Interarrival jitter in timestamp units, scaled by 16.
+    jitter        : u32,
+    transit       : Option<i64>,
+    started       : Option<Instant>,
+    last_sr       : u32,
+    last_sr_at    : Option<Instant>
+}
+
+impl ReceptionStats {
+    pub fn new (ssrc: u32, clock_rate: u32) -> ReceptionStats {
+        ReceptionStats {
+            ssrc, clock_rate, base_seq: 0, max_seq: 0, cycles: 0, received: 0, expected_prior: 0,
+            received_prior: 0, jitter: 0, transit: None, started: None, last_sr: 0, last_sr_at: None
+        }
+    }
+    /// Accounts for a packet from the source that arrived at `arrival`.
+    pub fn on_rtp(&mut self, packet: &rtp::Packet, arrival: Instant) {
+        let seq = packet.sequence_number();
+        let started = *self.started.get_or_insert(arrival);
+        if self.received == 0 {
+            self.base_seq = u32::from(seq);
+            self.max_seq = seq;
+        } else if seq.wrapping_sub(self.max_seq) < 0x8000 {
+            if seq < self.max_seq {
+                self.cycles = self.cycles.wrapping_add(1 << 16);
+            }
+            self.max_seq = seq;
+        }
+        self.received = self.received.wrapping_add(1);
+
+        let elapsed = arrival.saturating_duration_since(started);
+        let arrival = (elapsed.as_nanos() * u128::from(self.clock_rate) / 1_000_000_000) as i64;
+        let transit = arrival - i64::from(packet.timestamp());
+        if let Some(previous) = self.transit {
+            let d = (transit - previous).unsigned_abs() as u32;
+            self.jitter = self.jitter.wrapping_add(d.wrapping_sub((self.jitter + 8) >> 4));
+        }
+        self.transit = Some(transit);
+    }
+    /// Remembers a sender report from the source for LSR and DLSR.
+    pub fn on_sender_report(&mut self, report: &SenderReport, arrival: Instant) {
+        self.last_sr = (report.ntp_timestamp >> 16) as u32;
+        self.last_sr_at = Some(arrival);
+    }
+    /// The report block as of `now`, starting a new reporting interval.
+    pub fn report_block(&mut self, now: Instant) -> ReportBlock {
+        let extended = self.cycles | u32::from(self.max_seq);
+        let expected = if self.received == 0 { 0 } else { extended.wrapping_sub(self.base_seq).wrapping_add(1) };
+        let lost = i64::from(expected) - i64::from(self.received);
+        let expected_interval = expected.wrapping_sub(self.expected_prior);
+        let received_interval = self.received.wrapping_sub(self.received_prior);
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+        let lost_interval = i64::from(expected_interval) - i64::from(received_interval);
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / i64::from(expected_interval)).min(255) as u8
+        };
+        let delay = match self.last_sr_at {
+            Some(at) => (now.saturating_duration_since(at).as_nanos() * 65536 / 1_000_000_000) as u32,
+            None => 0
+        };
+        ReportBlock {
+            ssrc: self.ssrc,
+            fraction_lost,
+            cumulative_lost: lost.clamp(-0x80_0000, 0x7f_ffff) as i32,
+            highest_sequence: extended,
+            jitter: self.jitter >> 4,
+            last_sr: self.last_sr,
+            delay_since_last_sr: delay
+        }
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(invalid("RTCP packet is truncated"))
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+fn report_blocks(r: &mut Reader, count: u8) -> Result<Vec<ReportBlock>> {
+    let mut reports = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let ssrc = r.u32()?;
+        let lost = r.u32()?;
+        // Sign extend the 24 bit count.
+        let cumulative_lost = ((lost << 8) as i32) >> 8;
+        reports.push(ReportBlock {
+            ssrc,
+            fraction_lost: (lost >> 24) as u8,
+            cumulative_lost,
+            highest_sequence: r.u32()?,
+            jitter: r.u32()?,
+            last_sr: r.u32()?,
+            delay_since_last_sr: r.u32()?
+        });
+    }
+    Ok(reports)
+}
+
+fn put_report_blocks(buf: &mut Vec<u8>, reports: &[ReportBlock]) -> Result<usize> {
+    for report in reports {
+        put_u32(buf, report.ssrc);
+        put_u32(buf, u32::from(report.fraction_lost) << 24 | (report.cumulative_lost as u32 & 0x00ff_ffff));
+        put_u32(buf, report.highest_sequence);
+        put_u32(buf, report.jitter);
+        put_u32(buf, report.last_sr);
+        put_u32(buf, report.delay_since_last_sr);
+    }
+    Ok(reports.len())
+}
+
+fn sdes_chunk(r: &mut Reader) -> Result<SdesChunk> {
+    let start = r.buf.len();
+    let ssrc = r.u32()?;
+    let mut items = Vec::new();
+    loop {
+        let kind = r.take(1)?[0];
+        if kind == 0 {
+            break;
+        }
+        let len = r.take(1)?[0] as usize;
+        items.push(SdesItem { kind, text: String::from_utf8_lossy(r.take(len)?).into_owned() });
+    }
+    // Skip the padding after the null item to the next 32 bit boundary.
+    let used = start - r.buf.len();
+    r.take((4 - used % 4) % 4)?;
+    Ok(SdesChunk { ssrc, items })
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Zero pads the packet starting at `start` to 32 bits.
+fn pad(buf: &mut Vec<u8>, start: usize) {
+    while !(buf.len() - start).is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Rtcp(reason.to_string())
+}
+
+
+
+#[test]
+fn test() {
+    let sr = SenderReport {
+        ssrc: 0x1111,
+        ntp_timestamp: system_time_to_ntp(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+        rtp_timestamp: 90000,
+        packet_count: 10,
+        octet_count: 12000,
+        reports: vec![ReportBlock { ssrc: 0x2222, fraction_lost: 12, cumulative_lost: -3, highest_sequence: 70000,
+                                    jitter: 40, last_sr: 0xabcd, delay_since_last_sr: 65536 }]
+    };
+    let packets = vec![
+        Packet::SenderReport(sr.clone()),
+        Packet::SourceDescription(vec![SdesChunk::cname(0x1111, "camera@10.0.0.2"),
+                                       SdesChunk { ssrc: 0x3333, items: vec![] }]),
+        Packet::Nack(Nack { sender_ssrc: 1, media_ssrc: 2, lost: vec![100, 101, 116, 117, 65535, 3] }),
+        Packet::Pli(Pli { sender_ssrc: 1, media_ssrc: 2 }),
+        Packet::Fir(Fir { sender_ssrc: 1, entries: vec![(2, 7)] }),
+        Packet::Remb(Remb { sender_ssrc: 1, bitrate: 1_000_000, ssrcs: vec![2, 3] }),
+        Packet::App(App { subtype: 3, ssrc: 1, name: *b"TEST", data: vec![1, 2, 3, 4] }),
+        Packet::Bye(Bye { ssrcs: vec![0x1111], reason: Some("shutdown".to_string()) })
+    ];
+    let mut buf = Vec::new();
+    encode_compound(&packets, &mut buf).unwrap();
+    assert_eq!(buf.len() % 4, 0);
+    let parsed = parse_compound(&buf).unwrap();
+    assert_eq!(parsed.len(), packets.len());
+    assert_eq!(parsed[0], packets[0]);
+    assert_eq!(parsed[1], packets[1]);
+    match parsed[2] {
+        Packet::Nack(ref nack) => assert_eq!(nack.lost, vec![3, 100, 101, 116, 117, 65535]),
+        ref other => panic!("{:?}", other)
+    }
+    assert_eq!(&parsed[3..], &packets[3..]);
+
+    assert_eq!(sr.wallclock(), UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+    assert_eq!(sr.wallclock_at(90000 + 45000, 90000), UNIX_EPOCH + Duration::from_millis(1_600_000_000_500));
+    assert_eq!(sr.wallclock_at(0, 90000), UNIX_EPOCH + Duration::from_secs(1_599_999_999));
+
+    // A feedback message alone is only valid as reduced-size RTCP.
+    let mut buf = Vec::new();
+    Packet::Pli(Pli { sender_ssrc: 1, media_ssrc: 2 }).encode(&mut buf).unwrap();
+    assert!(parse_compound(&buf).is_err());
+    assert_eq!(Packets::new(&buf).count(), 1);
+    buf[3] = 9;
+    match Packets::new(&buf).next() {
+        Some(Err(Error::Rtcp(_))) => (),
+        other => panic!("{:?}", other)
+    }
+
+    // Failed encodes leave the buffer alone.
+    let broken = [
+        Packet::SourceDescription(vec![SdesChunk::cname(1, &"x".repeat(256))]),
+        Packet::Bye(Bye { ssrcs: vec![1], reason: Some("x".repeat(256)) }),
+        Packet::App(App { subtype: 0, ssrc: 1, name: *b"TEST", data: vec![1, 2, 3] }),
+        Packet::Remb(Remb { sender_ssrc: 1, bitrate: 0, ssrcs: vec![0; 256] }),
+        Packet::Unknown { packet_type: 210, count: 0, data: vec![1] }
+    ];
+    for packet in &broken {
+        let mut buf = vec![0xaa; 4];
+        assert!(packet.encode(&mut buf).is_err());
+        assert_eq!(buf, vec![0xaa; 4]);
+        assert!(encode_compound(&[packets[0].clone(), packet.clone()], &mut buf).is_err());
+        assert_eq!(buf, vec![0xaa; 4]);
+    }
+}
+
+#[test]
+fn reception() {
+    let mut stats = ReceptionStats::new(0x2222, 90000);
+    let start = Instant::now();
+    for (i, seq) in [65534u16, 65535, 1, 2].iter().enumerate() {
+        let buf = rtp::PacketBuilder::new(96, *seq, 2700 * i as u32, 0x2222).build(b"x").unwrap();
+        stats.on_rtp(&rtp::Packet::parse(&buf).unwrap(), start + Duration::from_millis(30 * i as u64));
+    }
+    let sr = SenderReport { ssrc: 0x2222, ntp_timestamp: 0x1234_5678_9abc_def0, rtp_timestamp: 0,
+                            packet_count: 0, octet_count: 0, reports: vec![] };
+    stats.on_sender_report(&sr, start);
+    let block = stats.report_block(start + Duration::from_millis(500));
+    assert_eq!(block.highest_sequence, 0x1_0002);
+    assert_eq!(block.cumulative_lost, 1);
+    assert_eq!(block.fraction_lost, 51);
+    assert_eq!(block.last_sr, 0x5678_9abc);
+    assert_eq!(block.delay_since_last_sr, 32768);
+    assert_eq!(block.jitter, 0);
+}
@@ -0,0 +1,350 @@
+// RTP: A Transport Protocol for Real-Time Applications
+// https://tools.ietf.org/html/rfc3550#section-5.1
+//
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |V=2|P|X|  CC   |M|     PT      |       sequence number         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                           timestamp                           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |           synchronization source (SSRC) identifier            |
+// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+// |            contributing source (CSRC) identifiers             |
+// |                             ....                              |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Header extensions, with the one-byte and two-byte element forms of
+// https://tools.ietf.org/html/rfc8285#section-4
+
+use error::{ Error, Result };
+
+pub const VERSION: u8 = 2;
+pub const HEADER_SIZE: usize = 12;
+
+/// The `defined by profile` value of one-byte header extensions.
+pub const ONE_BYTE_PROFILE: u16 = 0xBEDE;
+/// Two-byte header extensions use `0x100` followed by 4 application bits.
+pub const TWO_BYTE_PROFILE: u16 = 0x1000;
+
+/// An RTP packet borrowed from a receive buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet<'a> {
+    buf          : &'a [u8],
+    // Where the header extension's data and the payload sit in `buf`.
+    extension    : Option<(u16, usize, usize)>,
+    payload_start: usize,
+    payload_end  : usize
+}
+
+/// A header extension as carried in a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extension<'a> {
+    profile: u16,
+    data   : &'a [u8]
+}
+
+/// One RFC 8285 extension element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Element<'a> {
+    pub id  : u8,
+    pub data: &'a [u8]
+}
+
+/// Iterates over the elements of a one-byte or two-byte header extension.
+#[derive(Debug, Clone)]
+pub struct Elements<'a> {
+    data    : &'a [u8],
+    two_byte: bool
+}
+
+/// A header extension to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderExtension {
+    pub profile: u16,
+    /// Padded to whole 32 bit words when written.
+    pub data   : Vec<u8>
+}
+
+/// The header fields of a packet to send.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PacketBuilder {
+    pub marker         : bool,
+    pub payload_type   : u8,
+    pub sequence_number: u16,
+    pub timestamp      : u32,
+    pub ssrc           : u32,
+    pub csrcs          : Vec<u32>,
+    pub extension      : Option<HeaderExtension>,
+    /// Padding bytes to append, the last one holding the count.
+    pub padding        : u8
+}
+
+impl<'a> Packet<'a> {
+    /// Parses and validates `buf`, which holds exactly one packet.
+    pub fn parse(buf: &'a [u8]) -> Result<Packet<'a>> {
+        if buf.len() < HEADER_SIZE {
+            return Err(invalid("Packet is shorter than the RTP header"))
+        }
+        if buf[0] >> 6 != VERSION {
+            return Err(invalid("RTP version is not 2"))
+        }
+        let mut offset = HEADER_SIZE + 4 * (buf[0] & 0x0f) as usize;
+        if buf.len() < offset {
+            return Err(invalid("CSRC list exceeds the packet"))
+        }
+        let mut extension = None;
+        if buf[0] & 0x10 != 0 {
+            if buf.len() < offset + 4 {
+                return Err(invalid("Header extension exceeds the packet"))
+            }
+            let profile = u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+            let len = 4 * u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]) as usize;
+            offset += 4;
+            if buf.len() < offset + len {
+                return Err(invalid("Header extension exceeds the packet"))
+            }
+            extension = Some((profile, offset, offset + len));
+            offset += len;
+        }
+        let mut end = buf.len();
+        if buf[0] & 0x20 != 0 {
+            let padding = buf[end - 1] as usize;
+            if padding == 0 || offset + padding > end {
+                return Err(invalid("Invalid RTP padding"))
+            }
+            end -= padding;
+        }
+        Ok(Packet { buf, extension, payload_start: offset, payload_end: end })
+    }
+    pub fn version(&self) -> u8 {
+        self.buf[0] >> 6
+    }
+    pub fn has_padding(&self) -> bool {
+        self.buf[0] & 0x20 != 0
+    }
+    pub fn has_extension(&self) -> bool {
+        self.extension.is_some()
+    }
+    pub fn csrc_count(&self) -> u8 {
+        self.buf[0] & 0x0f
+    }
+    pub fn marker(&self) -> bool {
+        self.buf[1] & 0x80 != 0
+    }
+    pub fn payload_type(&self) -> u8 {
+        self.buf[1] & 0x7f
+    }
+    pub fn sequence_number(&self) -> u16 {
+        u16::from_be_bytes([self.buf[2], self.buf[3]])
+    }
+    pub fn timestamp(&self) -> u32 {
+        u32::from_be_bytes([self.buf[4], self.buf[5], self.buf[6], self.buf[7]])
+    }
+    pub fn ssrc(&self) -> u32 {
+        u32::from_be_bytes([self.buf[8], self.buf[9], self.buf[10], self.buf[11]])
+    }
+    pub fn csrcs(&self) -> impl Iterator<Item = u32> + 'a {
+        let end = HEADER_SIZE + 4 * self.csrc_count() as usize;
+        self.buf[HEADER_SIZE..end].chunks(4).map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+    }
+    pub fn extension(&self) -> Option<Extension<'a>> {
+        self.extension.map(|(profile, start, end)| Extension { profile, data: &self.buf[start..end] })
+    }
+    pub fn payload(&self) -> &'a [u8] {
+        &self.buf[self.payload_start..self.payload_end]
+    }
+    /// The number of padding bytes after the payload.
+    pub fn padding_len(&self) -> usize {
+        self.buf.len() - self.payload_end
+    }
+    /// The whole packet.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+impl<'a> Extension<'a> {
+    pub fn profile(&self) -> u16 {
+        self.profile
+    }
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+    /// The RFC 8285 elements, `None` for other kinds of extension.
+    pub fn elements(&self) -> Option<Elements<'a>> {
+        if self.profile == ONE_BYTE_PROFILE {
+            Some(Elements { data: self.data, two_byte: false })
+        } else if self.profile & 0xfff0 == TWO_BYTE_PROFILE {
+            Some(Elements { data: self.data, two_byte: true })
+        } else {
+            None
+        }
+    }
+    /// The data of element `id`, if present.
+    pub fn get(&self, id: u8) -> Option<&'a [u8]> {
+        self.elements().and_then(|mut elements| elements.find(|e| e.id == id)).map(|e| e.data)
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Element<'a>;
+    /// Stops at the first malformed element.
+    fn next(&mut self) -> Option<Element<'a>> {
+        loop {
+            // Padding between elements.
+            let first = *self.data.first()?;
+            if first == 0 {
+                self.data = &self.data[1..];
+                continue;
+            }
+            let (id, len, header) = if self.two_byte {
+                (first, *self.data.get(1)? as usize, 2)
+            } else {
+                // ID 15 ends the extension.
+                if first >> 4 == 15 {
+                    return None
+                }
+                (first >> 4, (first & 0x0f) as usize + 1, 1)
+            };
+            if self.data.len() < header + len {
+                self.data = &[];
+                return None
+            }
+            let data = &self.data[header..header + len];
+            self.data = &self.data[header + len..];
+            return Some(Element { id, data })
+        }
+    }
+}
+
+impl HeaderExtension {
+    /// One-byte elements, with ids 1 to 14 and 1 to 16 bytes of data.
+    pub fn one_byte(elements: &[(u8, &[u8])]) -> Result<HeaderExtension> {
+        let mut data = Vec::new();
+        for (id, value) in elements {
+            if *id == 0 || *id > 14 || value.is_empty() || value.len() > 16 {
+                return Err(invalid("Element does not fit a one-byte header extension"))
+            }
+            data.push(id << 4 | (value.len() - 1) as u8);
+            data.extend_from_slice(value);
+        }
+        Ok(HeaderExtension { profile: ONE_BYTE_PROFILE, data })
+    }
+    /// Two-byte elements, with ids 1 to 255 and up to 255 bytes of data.
+    pub fn two_byte(elements: &[(u8, &[u8])]) -> Result<HeaderExtension> {
+        let mut data = Vec::new();
+        for (id, value) in elements {
+            if *id == 0 || value.len() > 255 {
+                return Err(invalid("Element does not fit a two-byte header extension"))
+            }
+            data.push(*id);
+            data.push(value.len() as u8);
+            data.extend_from_slice(value);
+        }
+        Ok(HeaderExtension { profile: TWO_BYTE_PROFILE, data })
+    }
+}
+
+impl PacketBuilder {
+    pub fn new (payload_type: u8, sequence_number: u16, timestamp: u32, ssrc: u32) -> PacketBuilder {
+        PacketBuilder { payload_type, sequence_number, timestamp, ssrc, ..PacketBuilder::default() }
+    }
+    /// Appends the packet with `payload` to `buf`.
+    pub fn encode(&self, payload: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+        if self.payload_type > 0x7f {
+            return Err(invalid("Payload type does not fit in 7 bits"))
+        }
+        if self.csrcs.len() > 15 {
+            return Err(invalid("More than 15 CSRCs"))
+        }
+        let words = self.extension.as_ref().map(|e| e.data.len().div_ceil(4)).unwrap_or(0);
+        if words > 0xffff {
+            return Err(invalid("Header extension is too large"))
+        }
+        buf.push(VERSION << 6
+                 | if self.padding > 0 { 0x20 } else { 0 }
+                 | if self.extension.is_some() { 0x10 } else { 0 }
+                 | self.csrcs.len() as u8);
+        buf.push(if self.marker { 0x80 } else { 0 } | self.payload_type);
+        buf.extend_from_slice(&self.sequence_number.to_be_bytes());
+        buf.extend_from_slice(&self.timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        for csrc in &self.csrcs {
+            buf.extend_from_slice(&csrc.to_be_bytes());
+        }
+        if let Some(ref extension) = self.extension {
+            buf.extend_from_slice(&extension.profile.to_be_bytes());
+            buf.extend_from_slice(&(words as u16).to_be_bytes());
+            buf.extend_from_slice(&extension.data);
+            buf.resize(buf.len() + words * 4 - extension.data.len(), 0);
+        }
+        buf.extend_from_slice(payload);
+        if self.padding > 0 {
+            buf.resize(buf.len() + self.padding as usize - 1, 0);
+            buf.push(self.padding);
+        }
+        Ok(())
+    }
+    pub fn build(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(HEADER_SIZE + payload.len());
+        self.encode(payload, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::Rtp(reason.to_string())
+}
+
+
+
+#[test]
+fn test() {
+    let mut builder = PacketBuilder::new(96, 0xfffe, 3_000_000_000, 0x1234_5678);
+    builder.marker = true;
+    builder.csrcs = vec![1, 2];
+    builder.extension = Some(HeaderExtension::one_byte(&[(1, b"\x2a"), (3, b"abcd")]).unwrap());
+    builder.padding = 3;
+    let buf = builder.build(b"payload").unwrap();
+    assert_eq!(&buf[..2], &[0xb2, 0xe0]);
+
+    let packet = Packet::parse(&buf).unwrap();
+    assert_eq!(packet.version(), 2);
+    assert!(packet.marker());
+    assert_eq!(packet.payload_type(), 96);
+    assert_eq!(packet.sequence_number(), 0xfffe);
+    assert_eq!(packet.timestamp(), 3_000_000_000);
+    assert_eq!(packet.ssrc(), 0x1234_5678);
+    assert_eq!(packet.csrcs().collect::<Vec<u32>>(), vec![1, 2]);
+    assert_eq!(packet.payload(), b"payload");
+    assert_eq!(packet.padding_len(), 3);
+    let extension = packet.extension().unwrap();
+    assert_eq!(extension.profile(), ONE_BYTE_PROFILE);
+    assert_eq!(extension.data().len(), 8);
+    assert_eq!(extension.elements().unwrap().collect::<Vec<Element>>(),
+               vec![Element { id: 1, data: b"\x2a" }, Element { id: 3, data: b"abcd" }]);
+    assert_eq!(extension.get(3), Some(&b"abcd"[..]));
+
+    // Two-byte elements may be empty, padding between them is skipped.
+    let buf = b"\x90\x08\x00\x01\x00\x00\x00\x02\x00\x00\x00\x03\
+                \x10\x00\x00\x02\x05\x00\x00\x07\x02hi\x00\
+                \xff";
+    let packet = Packet::parse(buf).unwrap();
+    assert_eq!(packet.extension().unwrap().elements().unwrap().collect::<Vec<Element>>(),
+               vec![Element { id: 5, data: b"" }, Element { id: 7, data: b"hi" }]);
+    assert_eq!(packet.payload(), b"\xff");
+
+    for bad in [&b"\x80\x60\x00\x01"[..],
+                &b"\x40\x60\x00\x01\x00\x00\x00\x02\x00\x00\x00\x03"[..],
+                &b"\x81\x60\x00\x01\x00\x00\x00\x02\x00\x00\x00\x03"[..],
+                &b"\x90\x60\x00\x01\x00\x00\x00\x02\x00\x00\x00\x03\xbe\xde\x00\x01"[..],
+                &b"\xa0\x60\x00\x01\x00\x00\x00\x02\x00\x00\x00\x03\x00\x05"[..]].iter() {
+        match Packet::parse(bad) {
+            Err(Error::Rtp(_)) => (),
+            other => panic!("{:?}", other)
+        }
+    }
+    assert!(HeaderExtension::one_byte(&[(15, b"x")]).is_err());
+    assert!(PacketBuilder::new(128, 0, 0, 0).build(b"").is_err());
+}
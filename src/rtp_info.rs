@@ -0,0 +1,181 @@
+// RTP-Info header
+// https://tools.ietf.org/html/rfc2326#section-12.33
+//
+// RTP-Info        = "RTP-Info" ":" 1#stream-url 1*parameter
+// stream-url      = "url" "=" url
+// parameter       = ";" "seq" "=" 1*DIGIT
+//                 | ";" "rtptime" "=" 1*DIGIT
+//
+// For example:
+//   RTP-Info: url=rtsp://foo.com/bar.avi/streamid=0;seq=45102,
+//             url=rtsp://foo.com/bar.avi/streamid=1;seq=30211
+
+use std::fmt;
+use std::str::FromStr;
+
+use error::{ Error, Result };
+
+/// The RTP state of one stream at the start of playback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub url    : String,
+    pub seq    : Option<u16>,
+    pub rtptime: Option<u32>,
+    /// Sent by RTSP 2.0 servers and some 1.0 ones.
+    pub ssrc   : Option<u32>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RtpInfo(pub Vec<StreamInfo>);
+
+impl StreamInfo {
+    pub fn new (url: &str) -> StreamInfo {
+        StreamInfo { url: url.to_string(), seq: None, rtptime: None, ssrc: None }
+    }
+}
+
+impl RtpInfo {
+    /// Finds the stream for a control URL.
+    ///
+    /// Servers do not agree on whether to echo the absolute URL or just the
+    /// track part, so either one being a suffix of the other is a match.
+    pub fn find(&self, url: &str) -> Option<&StreamInfo> {
+        let url = url.trim_end_matches('/');
+        self.0.iter().find(|info| info.url.trim_end_matches('/') == url)
+            .or_else(|| self.0.iter().find(|info| {
+                let other = info.url.trim_end_matches('/');
+                !other.is_empty() && (url.ends_with(other) || other.ends_with(url))
+            }))
+    }
+}
+
+impl FromStr for StreamInfo {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<StreamInfo> {
+        let mut info = StreamInfo::new("");
+        for param in s.split(';').map(str::trim).filter(|param| !param.is_empty()) {
+            let (name, value) = match param.find('=') {
+                Some(i) => (param[..i].trim(), param[i + 1..].trim()),
+                None => continue
+            };
+            // Values are truncated rather than rejected, some servers send a
+            // 32 bit counter for `seq`.
+            match name.to_ascii_lowercase().as_ref() {
+                "url"     => info.url = value.trim_matches('"').to_string(),
+                "seq"     => info.seq = Some(number(value)? as u16),
+                "rtptime" => info.rtptime = Some(number(value)? as u32),
+                "ssrc"    => info.ssrc = Some(u32::from_str_radix(value, 16).map_err(|_| Error::Header)?),
+                _ => ()
+            }
+        }
+        Ok(info)
+    }
+}
+
+impl fmt::Display for StreamInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "url={}", self.url)?;
+        if let Some(seq) = self.seq {
+            write!(f, ";seq={}", seq)?;
+        }
+        if let Some(rtptime) = self.rtptime {
+            write!(f, ";rtptime={}", rtptime)?;
+        }
+        if let Some(ssrc) = self.ssrc {
+            write!(f, ";ssrc={:08X}", ssrc)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RtpInfo {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<RtpInfo> {
+        let streams = split_streams(s).into_iter()
+                                      .map(str::trim)
+                                      .filter(|stream| !stream.is_empty())
+                                      .map(StreamInfo::from_str)
+                                      .collect::<Result<Vec<StreamInfo>>>()?;
+        if streams.is_empty() {
+            return Err(Error::Header)
+        }
+        Ok(RtpInfo(streams))
+    }
+}
+
+impl fmt::Display for RtpInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, info) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", info)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the header into streams.
+///
+/// Unquoted URLs may contain commas, so a comma only starts a new stream
+/// when it is followed by `url=` and sits outside of double quotes.
+fn split_streams(s: &str) -> Vec<&str> {
+    let bytes = s.as_bytes();
+    let mut streams = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == b',' && !quoted {
+            let rest = s[i + 1..].trim_start();
+            if rest.as_bytes().get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case(b"url=")) {
+                streams.push(&s[start..i]);
+                start = i + 1;
+            }
+        }
+    }
+    streams.push(&s[start..]);
+    streams
+}
+
+fn number(s: &str) -> Result<u64> {
+    s.parse().map_err(|_| Error::Header)
+}
+
+
+
+#[test]
+fn test() {
+    let info: RtpInfo = "url=rtsp://foo.com/bar.avi/streamid=0;seq=45102;rtptime=12345678, \
+                         url=rtsp://foo.com/bar.avi/streamid=1;seq=30211".parse().unwrap();
+    assert_eq!(info.0.len(), 2);
+    assert_eq!(info.0[0].url, "rtsp://foo.com/bar.avi/streamid=0");
+    assert_eq!(info.0[0].seq, Some(45102));
+    assert_eq!(info.0[0].rtptime, Some(12345678));
+    assert_eq!(info.0[1].rtptime, None);
+    assert_eq!(info.to_string(), "url=rtsp://foo.com/bar.avi/streamid=0;seq=45102;rtptime=12345678,\
+                                  url=rtsp://foo.com/bar.avi/streamid=1;seq=30211");
+    assert_eq!(info.to_string().parse::<RtpInfo>().unwrap(), info);
+
+    // A multibyte character across the `url=` lookahead.
+    let info: RtpInfo = "url=a,abcé;seq=1".parse().unwrap();
+    assert_eq!(info.0.len(), 1);
+    assert_eq!(info.0[0].url, "a,abcé");
+
+    // Unquoted commas, quoted URLs, missing fields and oversized counters.
+    let info: RtpInfo = "url=rtsp://10.0.0.2/cam?ch=1,sub=0/trackID=1;seq=70000;rtptime=4294967296,\
+                         url=\"trackID=2\";rtptime=99;ssrc=1A2B3C4D".parse().unwrap();
+    assert_eq!(info.0[0].url, "rtsp://10.0.0.2/cam?ch=1,sub=0/trackID=1");
+    assert_eq!(info.0[0].seq, Some(4464));
+    assert_eq!(info.0[0].rtptime, Some(0));
+    assert_eq!(info.0[1].url, "trackID=2");
+    assert_eq!(info.0[1].seq, None);
+    assert_eq!(info.0[1].ssrc, Some(0x1A2B3C4D));
+
+    assert_eq!(info.find("rtsp://10.0.0.2/cam?ch=1,sub=0/trackID=2").map(|i| i.rtptime), Some(Some(99)));
+    assert_eq!(info.find("trackID=1").map(|i| i.seq), Some(Some(4464)));
+    assert!(info.find("trackID=3").is_none());
+
+    assert!("url=a;seq=x".parse::<RtpInfo>().is_err());
+}
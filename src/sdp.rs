@@ -0,0 +1,624 @@
+// SDP: Session Description Protocol
+// https://tools.ietf.org/html/rfc8866
+//
+// Session description
+//    v=  (protocol version)
+//    o=  (originator and session identifier)
+//    s=  (session name)
+//    i=* (session information)
+//    u=* (URI of description)
+//    e=* (email address)
+//    p=* (phone number)
+//    c=* (connection information -- not required if included in
+//         all media descriptions)
+//    b=* (zero or more bandwidth information lines)
+//    One or more time descriptions ("t=" and "r=" lines)
+//    z=* (time zone adjustments)
+//    k=* (obsolete)
+//    a=* (zero or more session attribute lines)
+//    Zero or more media descriptions
+//
+// Media description, if present
+//    m=  (media name and transport address)
+//    i=* (media title)
+//    c=* (connection information -- optional if included at
+//         session level)
+//    b=* (zero or more bandwidth information lines)
+//    k=* (obsolete)
+//    a=* (zero or more media attribute lines)
+//
+// Cameras get this wrong in every way imaginable, so parsing skips lines it
+// does not understand and only fails when there is nothing to describe.
+
+use std::fmt;
+use std::str::FromStr;
+
+use error::{ Error, Result };
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub username       : String,
+    pub session_id     : String,
+    pub session_version: String,
+    pub net_type       : String,
+    pub addr_type      : String,
+    pub address        : String
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub net_type : String,
+    pub addr_type: String,
+    /// Including any `/ttl` and `/number of addresses` suffix.
+    pub address  : String
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bandwidth {
+    /// `AS`, `CT`, `TIAS`, ...
+    pub bwtype   : String,
+    pub bandwidth: u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    pub start: u64,
+    pub stop : u64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    pub name : String,
+    pub value: Option<String>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    SendRecv,
+    SendOnly,
+    RecvOnly,
+    Inactive
+}
+
+/// `a=rtpmap:<payload type> <encoding name>/<clock rate>[/<encoding parameters>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpMap {
+    pub payload_type: u8,
+    pub encoding    : String,
+    pub clock_rate  : u32,
+    /// The channel count for audio.
+    pub params      : Option<u32>
+}
+
+/// `a=fmtp:<format> <format specific parameters>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fmtp {
+    pub format: String,
+    pub params: Vec<(String, String)>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Media {
+    /// `audio`, `video`, `application`, ...
+    pub media      : String,
+    pub port       : u16,
+    pub num_ports  : Option<u16>,
+    /// `RTP/AVP`, ...
+    pub protocol   : String,
+    /// RTP payload types for the RTP profiles.
+    pub formats    : Vec<String>,
+    pub title      : Option<String>,
+    pub connection : Option<Connection>,
+    pub bandwidths : Vec<Bandwidth>,
+    pub attributes : Vec<Attribute>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionDescription {
+    pub version     : u32,
+    pub origin      : Option<Origin>,
+    pub session_name: String,
+    pub information : Option<String>,
+    pub uri         : Option<String>,
+    pub emails      : Vec<String>,
+    pub phones      : Vec<String>,
+    pub connection  : Option<Connection>,
+    pub bandwidths  : Vec<Bandwidth>,
+    pub timings     : Vec<Timing>,
+    pub attributes  : Vec<Attribute>,
+    pub media       : Vec<Media>
+}
+
+impl Attribute {
+    pub fn new (name: &str, value: Option<&str>) -> Attribute {
+        Attribute { name: name.to_string(), value: value.map(|v| v.to_string()) }
+    }
+}
+
+impl Direction {
+    fn from_attributes(attributes: &[Attribute]) -> Option<Direction> {
+        attributes.iter().rev().find_map(|a| match a.name.as_ref() {
+            "sendrecv" => Some(Direction::SendRecv),
+            "sendonly" => Some(Direction::SendOnly),
+            "recvonly" => Some(Direction::RecvOnly),
+            "inactive" => Some(Direction::Inactive),
+            _ => None
+        })
+    }
+}
+
+impl AsRef<str> for Direction {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Direction::SendRecv => "sendrecv",
+            Direction::SendOnly => "sendonly",
+            Direction::RecvOnly => "recvonly",
+            Direction::Inactive => "inactive"
+        }
+    }
+}
+
+impl Fmtp {
+    /// The value of parameter `name`, compared case-insensitively.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_ref())
+    }
+}
+
+impl FromStr for RtpMap {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<RtpMap> {
+        let s = s.trim();
+        let space = s.find(char::is_whitespace).ok_or_else(|| invalid("rtpmap", s))?;
+        let payload_type = s[..space].parse().map_err(|_| invalid("rtpmap", s))?;
+        let mut parts = s[space..].trim().split('/').map(str::trim);
+        let encoding = parts.next().unwrap_or("");
+        if encoding.is_empty() {
+            return Err(invalid("rtpmap", s))
+        }
+        let clock_rate = parts.next().ok_or_else(|| invalid("rtpmap", s))?
+                              .parse().map_err(|_| invalid("rtpmap", s))?;
+        let params = match parts.next() {
+            Some(params) => Some(params.parse().map_err(|_| invalid("rtpmap", s))?),
+            None => None
+        };
+        Ok(RtpMap { payload_type, encoding: encoding.to_string(), clock_rate, params })
+    }
+}
+
+impl fmt::Display for RtpMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}/{}", self.payload_type, self.encoding, self.clock_rate)?;
+        if let Some(params) = self.params {
+            write!(f, "/{}", params)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fmtp {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Fmtp> {
+        let s = s.trim();
+        let (format, rest) = match s.find(char::is_whitespace) {
+            Some(i) => (&s[..i], s[i..].trim()),
+            None => (s, "")
+        };
+        if format.is_empty() {
+            return Err(invalid("fmtp", s))
+        }
+        let params = rest.split(';')
+                         .map(str::trim)
+                         .filter(|param| !param.is_empty())
+                         .map(|param| match param.find('=') {
+                             // Base64 values end in `=`, so only the first one separates.
+                             Some(i) => (param[..i].trim().to_string(), param[i + 1..].trim().to_string()),
+                             None => (param.to_string(), String::new())
+                         })
+                         .collect();
+        Ok(Fmtp { format: format.to_string(), params })
+    }
+}
+
+impl fmt::Display for Fmtp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.format)?;
+        for (i, (name, value)) in self.params.iter().enumerate() {
+            f.write_str(if i == 0 { " " } else { ";" })?;
+            if value.is_empty() {
+                f.write_str(name)?;
+            } else {
+                write!(f, "{}={}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Media {
+    pub fn new (media: &str, port: u16, protocol: &str, formats: Vec<String>) -> Media {
+        Media {
+            media: media.to_string(),
+            port,
+            num_ports: None,
+            protocol: protocol.to_string(),
+            formats,
+            title: None,
+            connection: None,
+            bandwidths: Vec::new(),
+            attributes: Vec::new()
+        }
+    }
+    /// The value of the first attribute `name`, `Some("")` for a flag.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        attribute(&self.attributes, name)
+    }
+    pub fn add_attribute(&mut self, name: &str, value: Option<&str>) {
+        self.attributes.push(Attribute::new(name, value));
+    }
+    /// The `a=control` URL, relative to the session's base URL.
+    pub fn control(&self) -> Option<&str> {
+        self.attribute("control")
+    }
+    /// Every well-formed `a=rtpmap`.
+    pub fn rtpmaps(&self) -> Vec<RtpMap> {
+        self.attributes.iter()
+                       .filter(|a| a.name == "rtpmap")
+                       .filter_map(|a| a.value.as_ref().and_then(|v| v.parse().ok()))
+                       .collect()
+    }
+    pub fn rtpmap(&self, payload_type: u8) -> Option<RtpMap> {
+        self.rtpmaps().into_iter().find(|rtpmap| rtpmap.payload_type == payload_type)
+    }
+    pub fn fmtp(&self, format: &str) -> Option<Fmtp> {
+        self.attributes.iter()
+                       .filter(|a| a.name == "fmtp")
+                       .filter_map(|a| a.value.as_ref().and_then(|v| v.parse::<Fmtp>().ok()))
+                       .find(|fmtp| fmtp.format == format)
+    }
+    /// `a=framerate`, or the `a=x-framerate` some cameras send instead.
+    pub fn framerate(&self) -> Option<f64> {
+        self.attribute("framerate").or_else(|| self.attribute("x-framerate"))
+            .and_then(|v| v.trim().parse().ok())
+    }
+    /// The media level direction attribute; the session level one applies
+    /// when this is `None`.
+    pub fn direction(&self) -> Option<Direction> {
+        Direction::from_attributes(&self.attributes)
+    }
+}
+
+impl SessionDescription {
+    pub fn new (session_name: &str) -> SessionDescription {
+        SessionDescription {
+            version: 0,
+            origin: None,
+            session_name: session_name.to_string(),
+            information: None,
+            uri: None,
+            emails: Vec::new(),
+            phones: Vec::new(),
+            connection: None,
+            bandwidths: Vec::new(),
+            timings: Vec::new(),
+            attributes: Vec::new(),
+            media: Vec::new()
+        }
+    }
+    /// Parses SDP from a message body, replacing invalid UTF-8.
+    pub fn from_bytes(body: &[u8]) -> Result<SessionDescription> {
+        String::from_utf8_lossy(body).parse()
+    }
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        attribute(&self.attributes, name)
+    }
+    pub fn add_attribute(&mut self, name: &str, value: Option<&str>) {
+        self.attributes.push(Attribute::new(name, value));
+    }
+    /// The session level `a=control`, `*` or an aggregate URL.
+    pub fn control(&self) -> Option<&str> {
+        self.attribute("control")
+    }
+    pub fn direction(&self) -> Option<Direction> {
+        Direction::from_attributes(&self.attributes)
+    }
+}
+
+impl FromStr for SessionDescription {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<SessionDescription> {
+        let mut sdp = SessionDescription::new("");
+        let mut seen_version = false;
+        // Inside a media section whose `m=` line did not parse, whose lines
+        // belong to neither the session nor the previous media.
+        let mut skipping = false;
+        for line in s.lines() {
+            let line = line.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            let bytes = line.as_bytes();
+            if bytes.len() < 2 || bytes[1] != b'=' {
+                continue;
+            }
+            let value = line[2..].trim();
+            if bytes[0] == b'm' {
+                skipping = match media(value) {
+                    Ok(media) => {
+                        sdp.media.push(media);
+                        false
+                    },
+                    Err(_) => true
+                };
+                continue;
+            }
+            if skipping {
+                continue;
+            }
+            if let Some(media) = sdp.media.last_mut() {
+                match bytes[0] {
+                    b'i' => media.title = Some(value.to_string()),
+                    b'c' => media.connection = connection(value).ok(),
+                    b'b' => media.bandwidths.extend(bandwidth(value).ok()),
+                    b'a' => media.attributes.push(attribute_line(value)),
+                    _ => ()
+                }
+                continue;
+            }
+            match bytes[0] {
+                b'v' => {
+                    seen_version = true;
+                    sdp.version = value.parse().unwrap_or(0);
+                },
+                b'o' => sdp.origin = origin(value).ok(),
+                b's' => sdp.session_name = value.to_string(),
+                b'i' => sdp.information = Some(value.to_string()),
+                b'u' => sdp.uri = Some(value.to_string()),
+                b'e' => sdp.emails.push(value.to_string()),
+                b'p' => sdp.phones.push(value.to_string()),
+                b'c' => sdp.connection = connection(value).ok(),
+                b'b' => sdp.bandwidths.extend(bandwidth(value).ok()),
+                b't' => sdp.timings.extend(timing(value).ok()),
+                b'a' => sdp.attributes.push(attribute_line(value)),
+                _ => ()
+            }
+        }
+        if !seen_version && sdp.media.is_empty() {
+            return Err(Error::Sdp("No session description found".to_string()))
+        }
+        Ok(sdp)
+    }
+}
+
+impl fmt::Display for SessionDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v={}\r\n", self.version)?;
+        match self.origin {
+            Some(ref o) => write!(f, "o={} {} {} {} {} {}\r\n", o.username, o.session_id,
+                                  o.session_version, o.net_type, o.addr_type, o.address)?,
+            None => f.write_str("o=- 0 0 IN IP4 127.0.0.1\r\n")?
+        }
+        // An empty s= is not allowed, a single space stands for no name.
+        write!(f, "s={}\r\n", if self.session_name.is_empty() { " " } else { &self.session_name })?;
+        if let Some(ref information) = self.information {
+            write!(f, "i={}\r\n", information)?;
+        }
+        if let Some(ref uri) = self.uri {
+            write!(f, "u={}\r\n", uri)?;
+        }
+        for email in &self.emails {
+            write!(f, "e={}\r\n", email)?;
+        }
+        for phone in &self.phones {
+            write!(f, "p={}\r\n", phone)?;
+        }
+        write_connection(f, &self.connection)?;
+        write_bandwidths(f, &self.bandwidths)?;
+        if self.timings.is_empty() {
+            f.write_str("t=0 0\r\n")?;
+        }
+        for timing in &self.timings {
+            write!(f, "t={} {}\r\n", timing.start, timing.stop)?;
+        }
+        write_attributes(f, &self.attributes)?;
+        for media in &self.media {
+            write!(f, "m={} {}", media.media, media.port)?;
+            if let Some(num_ports) = media.num_ports {
+                write!(f, "/{}", num_ports)?;
+            }
+            write!(f, " {}", media.protocol)?;
+            for format in &media.formats {
+                write!(f, " {}", format)?;
+            }
+            f.write_str("\r\n")?;
+            if let Some(ref title) = media.title {
+                write!(f, "i={}\r\n", title)?;
+            }
+            write_connection(f, &media.connection)?;
+            write_bandwidths(f, &media.bandwidths)?;
+            write_attributes(f, &media.attributes)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(what: &str, s: &str) -> Error {
+    Error::Sdp(format!("Invalid {} `{}`", what, s))
+}
+
+fn attribute<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|a| a.name.eq_ignore_ascii_case(name))
+              .map(|a| a.value.as_ref().map(|v| v.as_ref()).unwrap_or(""))
+}
+
+fn attribute_line(s: &str) -> Attribute {
+    match s.find(':') {
+        Some(i) => Attribute::new(s[..i].trim(), Some(s[i + 1..].trim())),
+        None => Attribute::new(s, None)
+    }
+}
+
+// m=<media> <port>[/<number of ports>] <proto> <fmt> ...
+fn media(s: &str) -> Result<Media> {
+    let mut parts = s.split_whitespace();
+    let name = parts.next().ok_or_else(|| invalid("media", s))?;
+    let port = parts.next().ok_or_else(|| invalid("media", s))?;
+    let (port, num_ports) = match port.find('/') {
+        Some(i) => (&port[..i], Some(port[i + 1..].parse().map_err(|_| invalid("media", s))?)),
+        None => (port, None)
+    };
+    let protocol = parts.next().unwrap_or("RTP/AVP");
+    let mut media = Media::new(name, port.parse().map_err(|_| invalid("media", s))?, protocol,
+                               parts.map(|format| format.to_string()).collect());
+    media.num_ports = num_ports;
+    Ok(media)
+}
+
+// o=<username> <sess-id> <sess-version> <nettype> <addrtype> <unicast-address>
+fn origin(s: &str) -> Result<Origin> {
+    let parts = s.split_whitespace().collect::<Vec<&str>>();
+    if parts.len() != 6 {
+        return Err(invalid("origin", s))
+    }
+    Ok(Origin {
+        username: parts[0].to_string(),
+        session_id: parts[1].to_string(),
+        session_version: parts[2].to_string(),
+        net_type: parts[3].to_string(),
+        addr_type: parts[4].to_string(),
+        address: parts[5].to_string()
+    })
+}
+
+// c=<nettype> <addrtype> <connection-address>
+fn connection(s: &str) -> Result<Connection> {
+    let parts = s.split_whitespace().collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        return Err(invalid("connection", s))
+    }
+    Ok(Connection {
+        net_type: parts[0].to_string(),
+        addr_type: parts[1].to_string(),
+        address: parts[2].to_string()
+    })
+}
+
+// b=<bwtype>:<bandwidth>
+fn bandwidth(s: &str) -> Result<Bandwidth> {
+    let colon = s.find(':').ok_or_else(|| invalid("bandwidth", s))?;
+    Ok(Bandwidth {
+        bwtype: s[..colon].trim().to_string(),
+        bandwidth: s[colon + 1..].trim().parse().map_err(|_| invalid("bandwidth", s))?
+    })
+}
+
+// t=<start-time> <stop-time>
+fn timing(s: &str) -> Result<Timing> {
+    let mut parts = s.split_whitespace().map(|part| part.parse::<u64>());
+    match (parts.next(), parts.next()) {
+        (Some(Ok(start)), Some(Ok(stop))) => Ok(Timing { start, stop }),
+        _ => Err(invalid("timing", s))
+    }
+}
+
+fn write_connection(f: &mut fmt::Formatter, connection: &Option<Connection>) -> fmt::Result {
+    match *connection {
+        Some(ref c) => write!(f, "c={} {} {}\r\n", c.net_type, c.addr_type, c.address),
+        None => Ok(())
+    }
+}
+
+fn write_bandwidths(f: &mut fmt::Formatter, bandwidths: &[Bandwidth]) -> fmt::Result {
+    for b in bandwidths {
+        write!(f, "b={}:{}\r\n", b.bwtype, b.bandwidth)?;
+    }
+    Ok(())
+}
+
+fn write_attributes(f: &mut fmt::Formatter, attributes: &[Attribute]) -> fmt::Result {
+    for a in attributes {
+        match a.value {
+            Some(ref value) => write!(f, "a={}:{}\r\n", a.name, value)?,
+            None => write!(f, "a={}\r\n", a.name)?
+        }
+    }
+    Ok(())
+}
+
+
+
+#[test]
+fn test() {
+    // Trimmed from a real camera: LF endings, stray spaces, x-framerate,
+    // an unknown line and a NUL terminator.
+    let text = "v=0\n\
+                o=- 1109162014219182 1109162014219192 IN IP4 192.168.1.64\n\
+                s=Media Presentation\n\
+                e=NONE\n\
+                b=AS:5050\n\
+                t=0 0\n\
+                a=control:rtsp://192.168.1.64/Streaming/Channels/101/\n\
+                q=vendor nonsense\n\
+                m=video 0 RTP/AVP 96\n\
+                c=IN IP4 0.0.0.0\n\
+                b=AS:5000\n\
+                a=recvonly\n\
+                a=x-dimensions:1920,1080\n\
+                a=control: trackID=1 \n\
+                a=rtpmap:96 H264/90000\n\
+                a=fmtp:96 profile-level-id=420029; packetization-mode=1; sprop-parameter-sets=Z00AKpWoHgCJ+WEAAAcIAAFfkAQ=,aO48gA==\n\
+                a=x-framerate:25\n\
+                m=audio 0 RTP/AVP 8 97\n\
+                a=rtpmap:8 PCMA/8000\n\
+                a=rtpmap:97 MPEG4-GENERIC/16000/2\n\
+                a=control:trackID=2\n\0";
+    let sdp: SessionDescription = text.parse().unwrap();
+    assert_eq!(sdp.origin.as_ref().unwrap().address, "192.168.1.64");
+    assert_eq!(sdp.session_name, "Media Presentation");
+    assert_eq!(sdp.bandwidths, vec![Bandwidth { bwtype: "AS".to_string(), bandwidth: 5050 }]);
+    assert_eq!(sdp.control(), Some("rtsp://192.168.1.64/Streaming/Channels/101/"));
+    assert_eq!(sdp.media.len(), 2);
+
+    let video = &sdp.media[0];
+    assert_eq!(video.media, "video");
+    assert_eq!(video.formats, vec!["96"]);
+    assert_eq!(video.control(), Some("trackID=1"));
+    assert_eq!(video.direction(), Some(Direction::RecvOnly));
+    assert_eq!(video.framerate(), Some(25.0));
+    assert_eq!(video.rtpmap(96), Some(RtpMap { payload_type: 96, encoding: "H264".to_string(), clock_rate: 90000, params: None }));
+    let fmtp = video.fmtp("96").unwrap();
+    assert_eq!(fmtp.get("packetization-mode"), Some("1"));
+    assert_eq!(fmtp.get("sprop-parameter-sets"), Some("Z00AKpWoHgCJ+WEAAAcIAAFfkAQ=,aO48gA=="));
+
+    let audio = &sdp.media[1];
+    assert_eq!(audio.rtpmap(97).unwrap().params, Some(2));
+    assert_eq!(audio.rtpmap(8).unwrap().clock_rate, 8000);
+    assert_eq!(audio.direction(), None);
+
+    // What we print parses back to the same description.
+    let printed = sdp.to_string();
+    assert!(printed.starts_with("v=0\r\no=- 1109162014219182 1109162014219192 IN IP4 192.168.1.64\r\ns=Media Presentation\r\n"));
+    assert_eq!(printed.parse::<SessionDescription>().unwrap(), sdp);
+
+    let mut sdp = SessionDescription::new("");
+    let mut media = Media::new("video", 0, "RTP/AVP", vec!["96".to_string()]);
+    media.add_attribute("rtpmap", Some("96 H264/90000"));
+    media.add_attribute("control", Some("track1"));
+    sdp.media.push(media);
+    assert_eq!(sdp.to_string(), "v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\ns= \r\nt=0 0\r\n\
+                                 m=video 0 RTP/AVP 96\r\na=rtpmap:96 H264/90000\r\na=control:track1\r\n");
+
+    // A media section whose `m=` line is broken is left out, with its
+    // attributes, and the next one is read as usual.
+    let text = "v=0\r\ns=-\r\nt=0 0\r\n\
+                m=video 0 RTP/AVP 96\r\na=control:trackID=1\r\n\
+                m=application port RTP/AVP 107\r\na=control:trackID=2\r\na=recvonly\r\n\
+                m=audio 0 RTP/AVP 0\r\na=control:trackID=3\r\n";
+    let sdp: SessionDescription = text.parse().unwrap();
+    assert_eq!(sdp.media.len(), 2);
+    assert_eq!(sdp.media[0].attributes, vec![Attribute::new("control", Some("trackID=1"))]);
+    assert_eq!(sdp.media[1].control(), Some("trackID=3"));
+    assert_eq!(sdp.direction(), None);
+
+    match "<html>not found</html>".parse::<SessionDescription>() {
+        Err(Error::Sdp(_)) => (),
+        other => panic!("{:?}", other)
+    }
+}
@@ -1,36 +1,684 @@
 
-use std::net::{ TcpStream, UdpSocket };
-use method::Method;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{ BuildHasher, Hasher };
+use std::io::Write;
+use std::net::{ SocketAddr, TcpListener, TcpStream, ToSocketAddrs };
+use std::sync::{ Arc, Mutex, MutexGuard };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use method::{ self, Method };
+use session::{ self, Session };
+use state::State;
+use status::StatusCode;
+use request::Request;
 use response::Response;
+use codec::{ Decoder, Message };
+use interleaved::Frame;
+use auth::Verifier;
+use error::{ Error, Result };
+
+/// Answers one method, given the handler and the request.
+pub type Callback<H> = fn(&H, &Request) -> Response;
+
+/// The application side of a server.
+///
+/// A handler registers a callback for each method it answers in
+/// `callbacks`. The server dispatches through that table and builds
+/// `Public` from it, so OPTIONS announces exactly the methods that are
+/// answered. Credentials and the session state are checked before a
+/// callback runs; afterwards the server echoes the `CSeq` and keeps the
+/// `Session` header. Methods without a callback are answered with 501.
+pub trait Handler: Sized + Send + Sync + 'static {
+    /// The callbacks to dispatch to, asked for once when the server binds.
+    fn callbacks(&self) -> Callbacks<Self>;
+    /// Interleaved data from the client, RTP and RTCP while recording.
+    fn data(&self, _frame: &Frame) {
+    }
+}
+
+/// A handler's callbacks by method, see `Handler`.
+///
+/// A new table answers OPTIONS and TEARDOWN with 200, and GET_PARAMETER
+/// with 200 when it is a keep-alive without a body. Registering a method
+/// again replaces its callback.
+pub struct Callbacks<H> {
+    callbacks: Vec<(Method, Callback<H>)>
+}
 
 // TCP
 #[derive(Debug)]
-pub struct Rtsp {
-    uri    : String,
-    session: Option<String>
+pub struct Rtsp<H: Handler> {
+    listener: TcpListener,
+    shared  : Arc<Shared<H>>
+}
+
+#[derive(Debug)]
+struct Shared<H: Handler> {
+    handler  : H,
+    callbacks: Callbacks<H>,
+    sessions : Mutex<Sessions>,
+    verifier : Option<Verifier>
+}
+
+/// A session as the server keeps it.
+#[derive(Debug, Clone)]
+pub struct ServerSession {
+    id       : String,
+    timeout  : u32,
+    state    : State,
+    last_seen: Instant
+}
+
+/// The server's sessions by id, moving each through the states of RFC 2326
+/// Appendix A as requests are answered.
+#[derive(Debug)]
+pub struct Sessions {
+    sessions: HashMap<String, ServerSession>,
+    timeout : u32,
+    ids     : RandomState,
+    next    : u64
 }
 
 // UDP
 #[derive(Debug)]
 pub struct Rtspu {
     uri    : String,
-    session: Option<String>
+    session: Option<Session>
+}
+
+
+impl<H> Callbacks<H> {
+    pub fn new () -> Callbacks<H> {
+        Callbacks { callbacks: Vec::new() }
+            .on(Method::Options, |_, _| Response::new())
+            .on(Method::Teardown, |_, _| Response::new())
+            .on(Method::GetParameter, keep_alive)
+    }
+    /// Answers `method` with `callback`.
+    pub fn on(mut self, method: Method, callback: Callback<H>) -> Callbacks<H> {
+        self.callbacks.retain(|(registered, _)| *registered != method);
+        self.callbacks.push((method, callback));
+        self
+    }
+    pub fn get(&self, method: &Method) -> Option<Callback<H>> {
+        self.callbacks.iter().find(|(registered, _)| registered == method).map(|&(_, callback)| callback)
+    }
+    /// The methods with a callback in the usual order, as announced in
+    /// `Public`.
+    pub fn methods(&self) -> Vec<Method> {
+        let order = [Method::Options, Method::Describe, Method::Announce, Method::Setup, Method::Play,
+                     Method::Pause, Method::Record, Method::Teardown, Method::GetParameter,
+                     Method::SetParameter, Method::Redirect];
+        let mut methods = self.callbacks.iter().map(|(method, _)| method.clone()).collect::<Vec<Method>>();
+        methods.sort_by_key(|method| order.iter().position(|m| m == method).unwrap_or(order.len()));
+        methods
+    }
+}
+
+impl<H> Default for Callbacks<H> {
+    fn default() -> Callbacks<H> {
+        Callbacks::new()
+    }
+}
+
+impl<H> fmt::Debug for Callbacks<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Callbacks").field("methods", &self.methods()).finish()
+    }
+}
+
+impl ServerSession {
+    pub fn new (id: &str, timeout: u32) -> ServerSession {
+        ServerSession { id: id.to_string(), timeout, state: State::Init, last_seen: Instant::now() }
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// The timeout in seconds.
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+    pub fn state(&self) -> State {
+        self.state
+    }
+    /// The `Session` header sent for this session.
+    pub fn header(&self) -> Session {
+        Session::with_timeout(&self.id, self.timeout)
+    }
+    /// Marks the session as alive, any request naming it does.
+    pub fn touch(&mut self) {
+        self.last_seen = Instant::now();
+    }
+    pub fn is_expired(&self) -> bool {
+        self.last_seen.elapsed() > Duration::from_secs(u64::from(self.timeout))
+    }
+    /// The `455 Method Not Valid in This State` answer to `request`, or
+    /// `None` when its method is valid in the current state.
+    pub fn check(&self, request: &Request) -> Option<Response> {
+        if self.state.is_valid(request.method()) {
+            return None
+        }
+        let mut response = reply(Some(request), StatusCode::MethodNotValidInThisState);
+        response.headers_mut().insert("Allow", method::join(&self.state.allowed()));
+        response.headers_mut().insert("Session", self.header().to_string());
+        Some(response)
+    }
+}
+
+impl Sessions {
+    /// Sessions time out after `session::DEFAULT_TIMEOUT` seconds.
+    pub fn new () -> Sessions {
+        Sessions::with_timeout(session::DEFAULT_TIMEOUT)
+    }
+    pub fn with_timeout(timeout: u32) -> Sessions {
+        Sessions { sessions: HashMap::new(), timeout, ids: RandomState::new(), next: 0 }
+    }
+    pub fn get(&self, id: &str) -> Option<&ServerSession> {
+        self.sessions.get(id)
+    }
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut ServerSession> {
+        self.sessions.get_mut(id)
+    }
+    /// Starts a session with a new, unguessable id.
+    pub fn create(&mut self) -> &mut ServerSession {
+        let id = loop {
+            let mut hasher = self.ids.build_hasher();
+            hasher.write_u64(self.next);
+            self.next += 1;
+            let id = format!("{:016X}", hasher.finish());
+            if !self.sessions.contains_key(&id) {
+                break id
+            }
+        };
+        self.insert(ServerSession::new(&id, self.timeout))
+    }
+    pub fn insert(&mut self, session: ServerSession) -> &mut ServerSession {
+        let id = session.id.clone();
+        self.sessions.insert(id.clone(), session);
+        self.sessions.get_mut(&id).expect("just inserted")
+    }
+    pub fn remove(&mut self, id: &str) -> Option<ServerSession> {
+        self.sessions.remove(id)
+    }
+    /// Removes and returns the sessions that timed out.
+    pub fn remove_expired(&mut self) -> Vec<ServerSession> {
+        let expired = self.sessions.values().filter(|s| s.is_expired()).map(|s| s.id.clone()).collect::<Vec<String>>();
+        expired.iter().filter_map(|id| self.sessions.remove(id)).collect()
+    }
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+    /// The answer to `request` if it may not be handled: `454 Session Not
+    /// Found` when it names an unknown session, `455` when its method is not
+    /// valid in the state of the session, or of a session yet to be set up.
+    pub fn check(&mut self, request: &Request) -> Option<Response> {
+        match session_id(request) {
+            Some(id) => match self.sessions.get_mut(&id) {
+                Some(session) => {
+                    session.touch();
+                    session.check(request)
+                },
+                None => Some(reply(Some(request), StatusCode::SessionNotFound))
+            },
+            None if State::Init.is_valid(request.method()) => None,
+            None => {
+                let mut response = reply(Some(request), StatusCode::MethodNotValidInThisState);
+                response.headers_mut().insert("Allow", method::join(&State::Init.allowed()));
+                Some(response)
+            }
+        }
+    }
+    /// Applies `response`, the server's answer to `request`.
+    ///
+    /// A successful SETUP outside of a session starts one, under the id the
+    /// response names or a new one, and the `Session` header is set. A
+    /// SETUP naming a live session joins it rather than starting over.
+    /// Other successful responses move the session on, a TEARDOWN ends it.
+    ///
+    /// Requests are checked before they are handled, but another request
+    /// on the same session may have been answered meanwhile. The state is
+    /// checked again here, and a response that no longer applies becomes
+    /// 454 or 455 as `check` would have answered.
+    pub fn update(&mut self, request: &Request, response: &mut Response) {
+        if !response.status().is_success() {
+            return
+        }
+        let id = match session_id(request) {
+            Some(id) => id,
+            None if *request.method() == Method::Setup => {
+                let given = response.headers().get("Session")
+                                    .and_then(|value| value.parse::<Session>().ok());
+                let session = match given {
+                    Some(ref given) if self.sessions.contains_key(given.id()) => {
+                        let session = self.sessions.get_mut(given.id()).expect("just looked up");
+                        session.touch();
+                        session
+                    },
+                    Some(given) => {
+                        let timeout = given.timeout().unwrap_or(self.timeout);
+                        self.insert(ServerSession::new(given.id(), timeout))
+                    },
+                    None => self.create()
+                };
+                session.state = session.state.next(request.method()).unwrap_or(session.state);
+                response.headers_mut().insert("Session", session.header().to_string());
+                return
+            },
+            None => return
+        };
+        let next = match self.sessions.get_mut(&id) {
+            Some(session) => {
+                if let Some(refused) = session.check(request) {
+                    *response = refused;
+                    return
+                }
+                session.state = session.state.next(request.method()).unwrap_or(session.state);
+                if !response.headers().contains("Session") {
+                    response.headers_mut().insert("Session", session.header().to_string());
+                }
+                session.state
+            },
+            None => {
+                *response = reply(Some(request), StatusCode::SessionNotFound);
+                return
+            }
+        };
+        if *request.method() == Method::Teardown || next == State::Init {
+            self.sessions.remove(&id);
+        }
+    }
+}
+
+impl Default for Sessions {
+    fn default() -> Sessions {
+        Sessions::new()
+    }
+}
+
+/// A bodiless response echoing the `CSeq` of `request`, if any.
+fn reply(request: Option<&Request>, status: StatusCode) -> Response {
+    let mut response = Response::with_status(status);
+    if let Some(cseq) = request.and_then(|request| request.cseq()) {
+        response.set_cseq(cseq);
+    }
+    response
+}
+
+/// Without a body GET_PARAMETER is a keep-alive, answered with 200.
+fn keep_alive<H>(_handler: &H, request: &Request) -> Response {
+    if request.body().is_empty() {
+        Response::new()
+    } else {
+        Response::with_status(StatusCode::ParameterNotUnderstood)
+    }
+}
+
+fn session_id(request: &Request) -> Option<String> {
+    request.headers().get("Session")
+           .map(|value| value.parse::<Session>().map(|s| s.id().to_string()).unwrap_or_else(|_| value.trim().to_string()))
 }
 
+impl<H: Handler> Rtsp<H> {
+    /// Listens on `addr`, port 554 being the usual one.
+    pub fn bind<A: ToSocketAddrs>(addr: A, handler: H) -> Result<Rtsp<H>> {
+        Rtsp::bind_with(addr, handler, None)
+    }
+    /// Like `bind`, answering every request without valid credentials
+    /// with `401 Unauthorized`.
+    pub fn bind_with_auth<A: ToSocketAddrs>(addr: A, handler: H, verifier: Verifier) -> Result<Rtsp<H>> {
+        Rtsp::bind_with(addr, handler, Some(verifier))
+    }
+    fn bind_with<A: ToSocketAddrs>(addr: A, handler: H, verifier: Option<Verifier>) -> Result<Rtsp<H>> {
+        Ok(Rtsp {
+            listener: TcpListener::bind(addr)?,
+            shared: Arc::new(Shared {
+                callbacks: handler.callbacks(),
+                handler,
+                sessions: Mutex::new(Sessions::new()),
+                verifier
+            })
+        })
+    }
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+    pub fn handler(&self) -> &H {
+        &self.shared.handler
+    }
+    pub fn sessions(&self) -> MutexGuard<'_, Sessions> {
+        self.shared.sessions()
+    }
+    /// Accepts connections forever, serving each on its own thread.
+    pub fn run(&self) -> Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let shared = self.shared.clone();
+            thread::spawn(move || shared.serve(stream));
+        }
+        Ok(())
+    }
+    /// Serves one connection until the client closes it.
+    pub fn serve(&self, stream: TcpStream) -> Result<()> {
+        self.shared.serve(stream)
+    }
+    /// Answers one request, without any I/O.
+    pub fn handle(&self, request: &Request) -> Response {
+        self.shared.handle(request)
+    }
+}
+
+impl<H: Handler> Shared<H> {
+    fn sessions(&self) -> MutexGuard<'_, Sessions> {
+        // A handler panicking elsewhere leaves the sessions consistent.
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    fn serve(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_nodelay(true)?;
+        let mut decoder = Decoder::new();
+        loop {
+            match decoder.decode() {
+                Ok(Some(Message::Request(request))) => self.handle(&request).write_to(&mut stream)?,
+                Ok(Some(Message::Data(frame))) => self.handler.data(&frame),
+                // Answers to requests the server never sends.
+                Ok(Some(Message::Response(_))) => (),
+                Ok(None) => {
+                    if decoder.read_from(&mut stream)? == 0 {
+                        return Ok(())
+                    }
+                },
+                Err(Error::Version) => reply(None, StatusCode::RTSPVersionNotSupported).write_to(&mut stream)?,
+                Err(Error::Io(e)) => return Err(Error::Io(e)),
+                Err(_) => {
+                    reply(None, StatusCode::BadRequest).write_to(&mut stream)?;
+                    if decoder.is_broken() {
+                        return Ok(())
+                    }
+                }
+            }
+            stream.flush()?;
+        }
+    }
+    fn handle(&self, request: &Request) -> Response {
+        if request.cseq().is_none() {
+            return reply(None, StatusCode::BadRequest)
+        }
+        // Handlers see who is calling in `Request::user`.
+        let request = match self.verifier {
+            Some(ref verifier) => match verifier.check(request) {
+                Ok(user) => {
+                    let mut request = request.clone();
+                    request.set_user(Some(user));
+                    Cow::Owned(request)
+                },
+                Err(response) => return response
+            },
+            None => Cow::Borrowed(request)
+        };
+        let request = &*request;
+        {
+            let mut sessions = self.sessions();
+            sessions.remove_expired();
+            if let Some(response) = sessions.check(request) {
+                return response
+            }
+        }
+        let mut response = match self.callbacks.get(request.method()) {
+            Some(callback) => callback(&self.handler, request),
+            // Only ever sent by servers.
+            None if *request.method() == Method::Redirect => Response::with_status(StatusCode::MethodNotAllowed),
+            None => Response::with_status(StatusCode::NotImplemented)
+        };
+        if *request.method() == Method::Options && !response.headers().contains("Public") {
+            response.headers_mut().insert("Public", method::join(&self.callbacks.methods()));
+        }
+        if let Some(cseq) = request.cseq() {
+            response.set_cseq(cseq);
+        }
+        self.sessions().update(request, &mut response);
+        response
+    }
+}
 
-impl Rtsp {
-    pub fn new (uri: &str) -> Rtsp {
-        Rtsp { uri: uri.to_string(), session: None }
+impl Rtspu {
+    pub fn new (uri: &str) -> Rtspu {
+        Rtspu { uri: uri.to_string(), session: None }
     }
     pub fn get_host(&self) -> String {
         self.uri.clone().to_string()
     }
-    pub fn request(&self, method: Method ) -> Result<Response, &'static str> {
-        if !(method.is_s_to_c()) {
-            // https://tools.ietf.org/html/rfc2326#section-10
-            // return "501 Not Implemented"
-            return Err("")
+    pub fn get_session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+}
+
+
+
+#[test]
+fn test() {
+    let request = |method: &str, session: Option<&str>, cseq: u32| {
+        let mut request = Request::new(method.parse().unwrap(), "rtsp://example.com/live");
+        request.set_cseq(cseq);
+        if let Some(session) = session {
+            request.headers_mut().insert("Session", session);
+        }
+        request
+    };
+    let mut sessions = Sessions::with_timeout(30);
+
+    let play = request("PLAY", None, 1);
+    let response = sessions.check(&play).unwrap();
+    assert_eq!(response.status(), StatusCode::MethodNotValidInThisState);
+    assert_eq!(response.cseq(), Some(1));
+    assert_eq!(response.headers().get("Allow").unwrap(),
+               "OPTIONS, DESCRIBE, ANNOUNCE, SETUP, TEARDOWN, GET_PARAMETER, SET_PARAMETER, REDIRECT");
+    assert_eq!(sessions.check(&request("PLAY", Some("nope"), 2)).unwrap().status(), StatusCode::SessionNotFound);
+
+    let setup = request("SETUP", None, 3);
+    assert!(sessions.check(&setup).is_none());
+    let mut response = Response::new();
+    sessions.update(&setup, &mut response);
+    let session = response.headers().get("Session").unwrap().parse::<Session>().unwrap();
+    assert_eq!(session.timeout(), Some(30));
+    assert_eq!(sessions.get(session.id()).unwrap().state(), State::Ready);
+
+    let id = Some(session.id());
+    let pause = request("PAUSE", id, 4);
+    let response = sessions.check(&pause).unwrap();
+    assert_eq!(response.status(), StatusCode::MethodNotValidInThisState);
+    assert!(!response.headers().get("Allow").unwrap().contains("PAUSE"));
+
+    for (method, state) in [("PLAY", State::Playing), ("SETUP", State::Playing), ("PAUSE", State::Ready),
+                                ("RECORD", State::Recording), ("GET_PARAMETER", State::Recording)] {
+        let request = request(method, id, 5);
+        assert!(sessions.check(&request).is_none(), "{}", method);
+        sessions.update(&request, &mut Response::new());
+        assert_eq!(sessions.get(session.id()).unwrap().state(), state);
+    }
+    // Failures leave the state alone.
+    sessions.update(&request("PAUSE", id, 6), &mut Response::with_status(StatusCode::InternalServerError));
+    assert_eq!(sessions.get(session.id()).unwrap().state(), State::Recording);
+
+    // A request that was fine when checked, but whose session moved on or
+    // ended before it was answered.
+    let pause = request("PAUSE", id, 7);
+    assert!(sessions.check(&pause).is_none());
+    sessions.update(&request("RECORD", id, 8), &mut Response::new());
+    sessions.update(&request("PAUSE", id, 9), &mut Response::new());
+    let mut response = Response::new();
+    sessions.update(&pause, &mut response);
+    assert_eq!(response.status(), StatusCode::MethodNotValidInThisState);
+    assert_eq!(response.cseq(), Some(7));
+    assert_eq!(sessions.get(session.id()).unwrap().state(), State::Ready);
+
+    // A SETUP answered with the id of a live session joins it.
+    let mut response = Response::new();
+    response.headers_mut().insert("Session", session.id());
+    sessions.update(&request("SETUP", None, 10), &mut response);
+    sessions.update(&request("PLAY", id, 11), &mut Response::new());
+    let mut response = Response::new();
+    response.headers_mut().insert("Session", session.id());
+    sessions.update(&request("SETUP", None, 12), &mut response);
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions.get(session.id()).unwrap().state(), State::Playing);
+
+    sessions.update(&request("TEARDOWN", id, 13), &mut Response::new());
+    assert!(sessions.is_empty());
+    let mut response = Response::new();
+    sessions.update(&request("PLAY", id, 14), &mut response);
+    assert_eq!(response.status(), StatusCode::SessionNotFound);
+}
+
+#[test]
+fn serve() {
+    use client;
+    use transport::Transport;
+
+    struct Camera;
+    impl Handler for Camera {
+        fn callbacks(&self) -> Callbacks<Camera> {
+            Callbacks::new().on(Method::Describe, Camera::describe)
+                            .on(Method::Setup, Camera::setup)
+                            .on(Method::Play, |_, _| Response::new())
+                            .on(Method::Extension("X-PTZ".to_string()), |_, _| Response::new())
+        }
+    }
+    impl Camera {
+        fn describe(&self, _request: &Request) -> Response {
+            let mut response = Response::new();
+            response.headers_mut().insert("Content-Type", "application/sdp");
+            response.set_body(b"v=0\r\ns=Camera\r\nm=video 0 RTP/AVP 96\r\na=control:track1\r\n".to_vec());
+            response
+        }
+        fn setup(&self, request: &Request) -> Response {
+            let mut response = Response::new();
+            response.headers_mut().insert("Transport", request.headers().get("Transport").unwrap().as_str());
+            response
+        }
+    }
+
+    let server = Arc::new(Rtsp::bind("127.0.0.1:0", Camera).unwrap());
+    let addr = server.local_addr().unwrap();
+    {
+        let server = server.clone();
+        thread::spawn(move || server.run());
+    }
+
+    let mut client = client::Rtsp::new(&format!("rtsp://{}/camera", addr)).unwrap();
+    let response = client.options().unwrap();
+    assert_eq!(response.cseq(), Some(1));
+    assert_eq!(response.headers().get("Public").unwrap(), "OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN, GET_PARAMETER, X-PTZ");
+    assert!(client.supports(&Method::GetParameter));
+    assert!(client.supports(&Method::Extension("X-PTZ".to_string())));
+    assert!(!client.supports(&Method::Record));
+    let response = client.describe().unwrap();
+    assert_eq!(response.cseq(), Some(2));
+    assert_eq!(response.headers().get("Content-Type").unwrap(), "application/sdp");
+    assert_eq!(client.record(None).unwrap_err().to_string(), "Method RECORD is not valid in state Init");
+
+    let response = client.setup("track1", &Transport::tcp_interleaved(0)).unwrap();
+    assert_eq!(response.headers().get("Transport").unwrap(), "RTP/AVP/TCP;unicast;interleaved=0-1");
+    let id = client.get_session().unwrap().id().to_string();
+    assert_eq!(server.sessions().get(&id).unwrap().state(), State::Ready);
+
+    assert!(client.play(None).unwrap().status().is_success());
+    assert_eq!(server.sessions().get(&id).unwrap().state(), State::Playing);
+    // The client would refuse to send this one itself.
+    let mut record = client.build_request(Method::Record);
+    record.set_cseq(9);
+    let response = server.handle(&record);
+    assert_eq!(response.status(), StatusCode::MethodNotValidInThisState);
+    assert_eq!(response.cseq(), Some(9));
+    assert_eq!(client.pause().unwrap().status(), StatusCode::NotImplemented);
+    assert_eq!(client.get_parameter(&[]).unwrap().status(), StatusCode::Ok);
+
+    assert!(client.teardown().unwrap().status().is_success());
+    assert!(server.sessions().is_empty());
+}
+
+#[test]
+fn public() {
+    fn options<H: Handler>(handler: H, method: Method) -> (Response, Response) {
+        let server = Rtsp::bind("127.0.0.1:0", handler).unwrap();
+        let mut request = Request::new(Method::Options, "*");
+        request.set_cseq(1);
+        let options = server.handle(&request);
+        let mut request = Request::new(method, "rtsp://example.com/live");
+        request.set_cseq(2);
+        (options, server.handle(&request))
+    }
+
+    // A method is announced exactly when it has a callback.
+    struct Describer;
+    impl Handler for Describer {
+        fn callbacks(&self) -> Callbacks<Describer> {
+            Callbacks::new().on(Method::SetParameter, |_, _| Response::new())
+                            .on(Method::Describe, |_, _| Response::new())
+        }
+    }
+    let (response, describe) = options(Describer, Method::Describe);
+    assert_eq!(response.cseq(), Some(1));
+    assert_eq!(response.headers().get("Public").unwrap(), "OPTIONS, DESCRIBE, TEARDOWN, GET_PARAMETER, SET_PARAMETER");
+    assert_eq!(describe.status(), StatusCode::Ok);
+
+    struct Bare;
+    impl Handler for Bare {
+        fn callbacks(&self) -> Callbacks<Bare> {
+            Callbacks::new()
         }
-        Ok(Response::new())
     }
-}
\ No newline at end of file
+    let (response, describe) = options(Bare, Method::Describe);
+    assert_eq!(response.headers().get("Public").unwrap(), "OPTIONS, TEARDOWN, GET_PARAMETER");
+    assert_eq!(describe.status(), StatusCode::NotImplemented);
+
+    // A `Public` the handler set itself is kept.
+    struct Own;
+    impl Handler for Own {
+        fn callbacks(&self) -> Callbacks<Own> {
+            Callbacks::new().on(Method::Options, |_, _| {
+                let mut response = Response::new();
+                response.headers_mut().insert("Public", "OPTIONS, PLAY");
+                response
+            })
+        }
+    }
+    assert_eq!(options(Own, Method::Options).0.headers().get("Public").unwrap(), "OPTIONS, PLAY");
+}
+
+#[test]
+fn user() {
+    use auth::{ CredentialStore, Verifier };
+
+    struct Users;
+    impl CredentialStore for Users {
+        fn password(&self, username: &str) -> Option<String> {
+            if username == "admin" { Some("12345".to_string()) } else { None }
+        }
+    }
+    // Answers with the user it was called by.
+    struct WhoAmI;
+    impl Handler for WhoAmI {
+        fn callbacks(&self) -> Callbacks<WhoAmI> {
+            Callbacks::new().on(Method::Describe, |_, request| {
+                let mut response = Response::new();
+                response.set_body(request.user().unwrap_or("").as_bytes().to_vec());
+                response
+            })
+        }
+    }
+    let mut verifier = Verifier::new("cam", Users);
+    verifier.set_basic(true);
+    let server = Rtsp::bind_with_auth("127.0.0.1:0", WhoAmI, verifier).unwrap();
+
+    let mut request = Request::new(Method::Describe, "rtsp://example.com/live");
+    request.set_cseq(1);
+    assert_eq!(server.handle(&request).status(), StatusCode::Unauthorized);
+    request.headers_mut().insert("Authorization", "Basic YWRtaW46MTIzNDU=");
+    assert_eq!(server.handle(&request).body(), b"admin");
+}
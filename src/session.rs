@@ -0,0 +1,94 @@
+// Session header
+// https://tools.ietf.org/html/rfc2326#section-12.37
+//
+// Session  = "Session" ":" session-id [ ";" "timeout" "=" delta-seconds ]
+//
+// For example:
+//   Session: 47112344;timeout=60
+
+use std::fmt;
+use std::str::FromStr;
+
+use error::{ Error, Result };
+
+/// The timeout a server is assumed to use when it does not send one.
+pub const DEFAULT_TIMEOUT: u32 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    id     : String,
+    timeout: Option<u32>
+}
+
+impl Session {
+    pub fn new (id: &str) -> Session {
+        Session { id: id.to_string(), timeout: None }
+    }
+    pub fn with_timeout(id: &str, timeout: u32) -> Session {
+        Session { id: id.to_string(), timeout: Some(timeout) }
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// The timeout in seconds as sent by the server.
+    pub fn timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+    /// The timeout in seconds, `DEFAULT_TIMEOUT` when none was sent.
+    pub fn timeout_or_default(&self) -> u32 {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+}
+
+impl FromStr for Session {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Session> {
+        let mut params = s.split(';').map(str::trim);
+        let id = params.next().unwrap_or("");
+        // session-id = 1*( ALPHA | DIGIT | safe ), some servers also use `.` and `+`.
+        if id.is_empty() || id.bytes().any(|b| b <= b' ' || b == b',' || b >= 0x7f) {
+            return Err(Error::Header)
+        }
+        let mut session = Session::new(id);
+        for param in params {
+            let mut kv = param.splitn(2, '=').map(str::trim);
+            if kv.next().unwrap_or("").eq_ignore_ascii_case("timeout") {
+                // Anything after the number, such as `timeout=60s`, is ignored.
+                let value = kv.next().unwrap_or("");
+                let digits = value.bytes().take_while(|b| b.is_ascii_digit()).count();
+                session.timeout = Some(value[..digits].parse().map_err(|_| Error::Header)?);
+            }
+        }
+        Ok(session)
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.id)?;
+        if let Some(timeout) = self.timeout {
+            write!(f, ";timeout={}", timeout)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+#[test]
+fn test() {
+    let session: Session = "47112344;timeout=60".parse().unwrap();
+    assert_eq!(session.id(), "47112344");
+    assert_eq!(session.timeout(), Some(60));
+    assert_eq!(session.to_string(), "47112344;timeout=60");
+
+    let session: Session = " 6B8B4567 ; Timeout = 30 ; x-vendor=1".parse().unwrap();
+    assert_eq!(session, Session::with_timeout("6B8B4567", 30));
+
+    let session: Session = "A1B2C3".parse().unwrap();
+    assert_eq!(session.timeout_or_default(), DEFAULT_TIMEOUT);
+    assert_eq!(session.to_string(), "A1B2C3");
+
+    assert!("".parse::<Session>().is_err());
+    assert!("abc;timeout=soon".parse::<Session>().is_err());
+}
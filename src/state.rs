@@ -0,0 +1,102 @@
+// Session states
+// https://tools.ietf.org/html/rfc2326#appendix-A
+//
+// The client changes state on receipt of a successful reply to a request,
+// the server when it sends one. Both follow the same table:
+//
+//   state      message sent   next state
+//   Init       SETUP          Ready
+//              TEARDOWN       Init
+//   Ready      PLAY           Playing
+//              RECORD         Recording
+//              TEARDOWN       Init
+//              SETUP          Ready
+//   Playing    PLAY           Playing
+//              PAUSE          Ready
+//              TEARDOWN       Init
+//              SETUP          Playing
+//   Recording  RECORD         Recording
+//              PAUSE          Ready
+//              TEARDOWN       Init
+//              SETUP          Recording
+//
+// Methods that do not touch the session state, such as OPTIONS, DESCRIBE
+// and GET_PARAMETER, are valid in every state. REDIRECT ends the session.
+
+use std::fmt;
+
+use method::Method;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum State {
+    #[default]
+    Init,
+    Ready,
+    Playing,
+    Recording
+}
+
+impl State {
+    /// The state after a successful `method`, `None` when `method` is not
+    /// valid in this state.
+    pub fn next(&self, method: &Method) -> Option<State> {
+        match (*self, method) {
+            (_, &Method::Teardown) | (_, &Method::Redirect) => Some(State::Init),
+            (State::Init, &Method::Setup) => Some(State::Ready),
+            (state, &Method::Setup) => Some(state),
+            (State::Ready, &Method::Play) | (State::Playing, &Method::Play) => Some(State::Playing),
+            (State::Ready, &Method::Record) | (State::Recording, &Method::Record) => Some(State::Recording),
+            (State::Playing, &Method::Pause) | (State::Recording, &Method::Pause) => Some(State::Ready),
+            (_, &Method::Play) | (_, &Method::Record) | (_, &Method::Pause) => None,
+            (state, _) => Some(state)
+        }
+    }
+    pub fn is_valid(&self, method: &Method) -> bool {
+        self.next(method).is_some()
+    }
+    /// The standard methods valid in this state, as listed in `Allow`.
+    pub fn allowed(&self) -> Vec<Method> {
+        [Method::Options, Method::Describe, Method::Announce, Method::Setup, Method::Play,
+         Method::Pause, Method::Record, Method::Teardown, Method::GetParameter,
+         Method::SetParameter, Method::Redirect]
+            .iter().filter(|method| self.is_valid(method)).cloned().collect()
+    }
+}
+
+impl AsRef<str> for State {
+    fn as_ref(&self) -> &str {
+        match *self {
+            State::Init      => "Init",
+            State::Ready     => "Ready",
+            State::Playing   => "Playing",
+            State::Recording => "Recording"
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+
+
+#[test]
+fn test() {
+    assert_eq!(State::Init.next(&Method::Play), None);
+    assert_eq!(State::Init.next(&Method::Pause), None);
+    assert_eq!(State::Init.next(&Method::Setup), Some(State::Ready));
+    assert_eq!(State::Ready.next(&Method::Setup), Some(State::Ready));
+    assert_eq!(State::Ready.next(&Method::Play), Some(State::Playing));
+    assert_eq!(State::Ready.next(&Method::Pause), None);
+    assert_eq!(State::Playing.next(&Method::Setup), Some(State::Playing));
+    assert_eq!(State::Playing.next(&Method::Record), None);
+    assert_eq!(State::Playing.next(&Method::Pause), Some(State::Ready));
+    assert_eq!(State::Recording.next(&Method::Record), Some(State::Recording));
+    assert_eq!(State::Recording.next(&Method::Teardown), Some(State::Init));
+    assert_eq!(State::Recording.next(&Method::GetParameter), Some(State::Recording));
+    assert_eq!(State::Ready.allowed(), vec![Method::Options, Method::Describe, Method::Announce, Method::Setup,
+                                            Method::Play, Method::Record, Method::Teardown,
+                                            Method::GetParameter, Method::SetParameter, Method::Redirect]);
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::cmp::Ordering;
+use std::hash::{ Hash, Hasher };
 
 
 use ::method::Method;
@@ -24,9 +25,10 @@ pub enum StatusClass {
 }
 
 // #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
-#[derive(Debug, Hash)]
+#[derive(Debug, Default)]
 pub enum StatusCode {
     Continue,         // 100
+    #[default]
     Ok,               // 200
     Created,          // 201
     LowOnStorageSpace,// 250
@@ -224,11 +226,11 @@ impl StatusCode {
     }
     pub fn class(&self) -> StatusClass {
         match self.to_u16() {
-            100...199 => StatusClass::Informational,
-            200...299 => StatusClass::Success,
-            300...399 => StatusClass::Redirection,
-            400...499 => StatusClass::ClientError,
-            500...599 => StatusClass::ServerError,
+            100..=199 => StatusClass::Informational,
+            200..=299 => StatusClass::Success,
+            300..=399 => StatusClass::Redirection,
+            400..=499 => StatusClass::ClientError,
+            500..=599 => StatusClass::ServerError,
             _         => StatusClass::NoClass,
         }
     }
@@ -237,18 +239,8 @@ impl StatusCode {
         match *self {
             StatusCode::Continue => true,  // all methods
             StatusCode::Ok       => true,
-            StatusCode::Created  => {
-                match method {
-                    Method::Record => true,
-                    _              => false
-                }
-            },
-            StatusCode::LowOnStorageSpace => {
-                match method {
-                    Method::Record => true,
-                    _              => false
-                }
-            },
+            StatusCode::Created  => matches!(method, Method::Record),
+            StatusCode::LowOnStorageSpace => matches!(method, Method::Record),
             StatusCode::MultipleChoices   => true,
             StatusCode::MovedPermanently  => true,
             StatusCode::MovedTemporarily  => true,
@@ -266,38 +258,18 @@ impl StatusCode {
             StatusCode::RequestTimeout   => true,
             StatusCode::Gone             => true,
             StatusCode::LengthRequired         => true,
-            StatusCode::PreconditionFailed     => {
-                match method {
-                    Method::Describe | Method::Setup => true,
-                    _                => false
-                }
-            },
+            StatusCode::PreconditionFailed     => matches!(method, Method::Describe | Method::Setup),
             StatusCode::RequestEntityTooLarge  => true,
             StatusCode::RequestURITooLarge     => true,
             StatusCode::UnsupportedMediaType   => true,
-            StatusCode::ParameterNotUnderstood => match method {
-                Method::Setup => true,
-                _             => false
-            },
-            StatusCode::ConferenceNotFound     => match method {
-                Method::Setup => true,
-                _             => false
-            },
-            StatusCode::NotEnoughBandwidth     => match method {
-                Method::Setup => true,
-                _             => false
-            },
+            StatusCode::ParameterNotUnderstood => matches!(method, Method::Setup),
+            StatusCode::ConferenceNotFound     => matches!(method, Method::Setup),
+            StatusCode::NotEnoughBandwidth     => matches!(method, Method::Setup),
             StatusCode::SessionNotFound        => true,
             StatusCode::MethodNotValidInThisState      => true,
             StatusCode::HeaderFieldNotValidForResource => true,
-            StatusCode::InvalidRange                   => match method {
-                Method::Play => true,
-                _             => false
-            },
-            StatusCode::ParameterIsReadOnly            => match method {
-                Method::SetParameter => true,
-                _             => false
-            },
+            StatusCode::InvalidRange                   => matches!(method, Method::Play),
+            StatusCode::ParameterIsReadOnly            => matches!(method, Method::SetParameter),
             StatusCode::AggregateOperationNotAllowed   => true,
             StatusCode::OnlyAggregateOperationAllowed  => true,
             StatusCode::UnsupportedTransport    => true,
@@ -351,6 +323,12 @@ impl PartialEq for StatusCode {
 
 impl Eq for StatusCode {}
 
+impl Hash for StatusCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_u16().hash(state)
+    }
+}
+
 impl Clone for StatusCode {
     #[inline]
     fn clone(&self) -> StatusCode {
@@ -362,25 +340,13 @@ impl Clone for StatusCode {
 impl PartialOrd for StatusCode {
     #[inline]
     fn partial_cmp(&self, other: &StatusCode) -> Option<Ordering> {
-        self.to_u16().partial_cmp(&(other.to_u16()))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for StatusCode {
     #[inline]
     fn cmp(&self, other: &StatusCode) -> Ordering {
-        if *self < *other {
-            Ordering::Less
-        } else if *self > *other {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
-    }
-}
-
-impl Default for StatusCode {
-    fn default() -> StatusCode {
-        StatusCode::Ok
+        self.to_u16().cmp(&(other.to_u16()))
     }
 }
@@ -0,0 +1,348 @@
+// Transport header
+// https://tools.ietf.org/html/rfc2326#section-12.39
+//
+// Transport           =    "Transport" ":"
+//                          1\#transport-spec
+// transport-spec      =    transport-protocol/profile[/lower-transport]
+//                          *parameter
+// parameter           =    ( "unicast" | "multicast" )
+//                     |    ";" "destination" [ "=" address ]
+//                     |    ";" "interleaved" "=" channel [ "-" channel ]
+//                     |    ";" "append"
+//                     |    ";" "ttl" "=" ttl
+//                     |    ";" "layers" "=" 1*DIGIT
+//                     |    ";" "port" "=" port [ "-" port ]
+//                     |    ";" "client_port" "=" port [ "-" port ]
+//                     |    ";" "server_port" "=" port [ "-" port ]
+//                     |    ";" "ssrc" "=" ssrc
+//                     |    ";" "mode" = <"> 1\#mode <">
+//
+// For example:
+//   Transport: RTP/AVP;multicast;ttl=127;mode="PLAY",
+//              RTP/AVP;unicast;client_port=3456-3457;mode="PLAY"
+
+use std::fmt;
+use std::str::FromStr;
+
+use method::Method;
+use error::{ Error, Result };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// `RTP/AVP`, RFC 3551
+    Avp,
+    /// `RTP/SAVP`, RFC 3711
+    Savp,
+    /// `RTP/AVPF`, RFC 4585
+    Avpf,
+    /// `RTP/SAVPF`, RFC 5124
+    Savpf
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LowerTransport {
+    Udp,
+    Tcp
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cast {
+    Unicast,
+    Multicast
+}
+
+/// A port or channel pair such as `client_port=3456-3457`.
+pub type Pair<T> = (T, Option<T>);
+
+/// A single transport-spec.
+///
+/// Fields are `None` when the parameter was not given, so printing a
+/// parsed value writes the same parameters back, in a fixed order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transport {
+    pub profile: Profile,
+    /// UDP when `None`.
+    pub lower_transport: Option<LowerTransport>,
+    pub cast: Option<Cast>,
+    /// `Some("")` for a `destination` without an address.
+    pub destination: Option<String>,
+    pub source: Option<String>,
+    pub interleaved: Option<Pair<u8>>,
+    pub append: bool,
+    pub ttl: Option<u8>,
+    pub layers: Option<u32>,
+    pub port: Option<Pair<u16>>,
+    pub client_port: Option<Pair<u16>>,
+    pub server_port: Option<Pair<u16>>,
+    pub ssrc: Option<u32>,
+    pub mode: Vec<Method>,
+    /// Parameters this type does not know about, kept for round trips.
+    pub extensions: Vec<(String, Option<String>)>
+}
+
+impl Transport {
+    pub fn new (profile: Profile) -> Transport {
+        Transport {
+            profile,
+            lower_transport: None,
+            cast: None,
+            destination: None,
+            source: None,
+            interleaved: None,
+            append: false,
+            ttl: None,
+            layers: None,
+            port: None,
+            client_port: None,
+            server_port: None,
+            ssrc: None,
+            mode: Vec::new(),
+            extensions: Vec::new()
+        }
+    }
+    /// `RTP/AVP;unicast;client_port=<rtp>-<rtp + 1>`
+    pub fn udp_unicast(rtp_port: u16) -> Transport {
+        let mut transport = Transport::new(Profile::Avp);
+        transport.cast = Some(Cast::Unicast);
+        transport.client_port = Some((rtp_port, Some(rtp_port.wrapping_add(1))));
+        transport
+    }
+    /// `RTP/AVP/TCP;unicast;interleaved=<rtp>-<rtp + 1>`
+    pub fn tcp_interleaved(rtp_channel: u8) -> Transport {
+        let mut transport = Transport::new(Profile::Avp);
+        transport.lower_transport = Some(LowerTransport::Tcp);
+        transport.cast = Some(Cast::Unicast);
+        transport.interleaved = Some((rtp_channel, Some(rtp_channel.wrapping_add(1))));
+        transport
+    }
+    pub fn is_tcp(&self) -> bool {
+        self.lower_transport == Some(LowerTransport::Tcp)
+    }
+    pub fn is_multicast(&self) -> bool {
+        self.cast == Some(Cast::Multicast)
+    }
+}
+
+impl FromStr for Transport {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Transport> {
+        let mut params = split_quoted(s, b';').into_iter().map(str::trim);
+
+        let spec = params.next().unwrap_or("");
+        let mut parts = spec.split('/');
+        if !parts.next().unwrap_or("").eq_ignore_ascii_case("RTP") {
+            return Err(Error::Header)
+        }
+        let profile = match parts.next().map(|p| p.to_ascii_uppercase()) {
+            Some(ref p) if p == "AVP"   => Profile::Avp,
+            Some(ref p) if p == "SAVP"  => Profile::Savp,
+            Some(ref p) if p == "AVPF"  => Profile::Avpf,
+            Some(ref p) if p == "SAVPF" => Profile::Savpf,
+            _ => return Err(Error::Header)
+        };
+        let mut transport = Transport::new(profile);
+        transport.lower_transport = match parts.next().map(|p| p.to_ascii_uppercase()) {
+            None => None,
+            Some(ref p) if p == "UDP" => Some(LowerTransport::Udp),
+            Some(ref p) if p == "TCP" => Some(LowerTransport::Tcp),
+            _ => return Err(Error::Header)
+        };
+        if parts.next().is_some() {
+            return Err(Error::Header)
+        }
+
+        for param in params.filter(|param| !param.is_empty()) {
+            let (name, value) = match param.find('=') {
+                Some(i) => (param[..i].trim(), Some(param[i + 1..].trim())),
+                None => (param, None)
+            };
+            match (name.to_ascii_lowercase().as_ref(), value) {
+                ("unicast", None)   => transport.cast = Some(Cast::Unicast),
+                ("multicast", None) => transport.cast = Some(Cast::Multicast),
+                ("append", None)    => transport.append = true,
+                ("destination", value) => transport.destination = Some(value.unwrap_or("").to_string()),
+                ("source", Some(value))      => transport.source = Some(value.to_string()),
+                ("interleaved", Some(value)) => transport.interleaved = Some(pair(value)?),
+                ("ttl", Some(value))         => transport.ttl = Some(number(value)?),
+                ("layers", Some(value))      => transport.layers = Some(number(value)?),
+                ("port", Some(value))        => transport.port = Some(pair(value)?),
+                ("client_port", Some(value)) => transport.client_port = Some(pair(value)?),
+                ("server_port", Some(value)) => transport.server_port = Some(pair(value)?),
+                ("ssrc", Some(value)) => {
+                    transport.ssrc = Some(u32::from_str_radix(value, 16).map_err(|_| Error::Header)?)
+                },
+                ("mode", Some(value)) => {
+                    for mode in value.trim_matches('"').split(',').map(str::trim) {
+                        transport.mode.push(Method::from_str(&mode.to_ascii_uppercase())?);
+                    }
+                },
+                _ => transport.extensions.push((name.to_string(), value.map(|v| v.to_string())))
+            }
+        }
+        Ok(transport)
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self.profile {
+            Profile::Avp   => "RTP/AVP",
+            Profile::Savp  => "RTP/SAVP",
+            Profile::Avpf  => "RTP/AVPF",
+            Profile::Savpf => "RTP/SAVPF"
+        })?;
+        match self.lower_transport {
+            Some(LowerTransport::Udp) => f.write_str("/UDP")?,
+            Some(LowerTransport::Tcp) => f.write_str("/TCP")?,
+            None => ()
+        }
+        match self.cast {
+            Some(Cast::Unicast)   => f.write_str(";unicast")?,
+            Some(Cast::Multicast) => f.write_str(";multicast")?,
+            None => ()
+        }
+        match self.destination {
+            Some(ref d) if d.is_empty() => f.write_str(";destination")?,
+            Some(ref d) => write!(f, ";destination={}", d)?,
+            None => ()
+        }
+        if let Some(ref source) = self.source {
+            write!(f, ";source={}", source)?;
+        }
+        write_pair(f, "interleaved", self.interleaved)?;
+        if self.append {
+            f.write_str(";append")?;
+        }
+        if let Some(ttl) = self.ttl {
+            write!(f, ";ttl={}", ttl)?;
+        }
+        if let Some(layers) = self.layers {
+            write!(f, ";layers={}", layers)?;
+        }
+        write_pair(f, "port", self.port)?;
+        write_pair(f, "client_port", self.client_port)?;
+        write_pair(f, "server_port", self.server_port)?;
+        if let Some(ssrc) = self.ssrc {
+            write!(f, ";ssrc={:08X}", ssrc)?;
+        }
+        if !self.mode.is_empty() {
+            let modes = self.mode.iter().map(|m| m.as_ref()).collect::<Vec<&str>>();
+            write!(f, ";mode=\"{}\"", modes.join(","))?;
+        }
+        for (name, value) in &self.extensions {
+            match value {
+                Some(value) => write!(f, ";{}={}", name, value)?,
+                None => write!(f, ";{}", name)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The full header value: one or more transport-specs in order of preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transports(pub Vec<Transport>);
+
+impl FromStr for Transports {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Transports> {
+        let specs = split_quoted(s, b',').into_iter()
+                                         .map(str::trim)
+                                         .filter(|spec| !spec.is_empty())
+                                         .map(Transport::from_str)
+                                         .collect::<Result<Vec<Transport>>>()?;
+        if specs.is_empty() {
+            return Err(Error::Header)
+        }
+        Ok(Transports(specs))
+    }
+}
+
+impl fmt::Display for Transports {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, transport) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", transport)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits on `sep` outside of double quotes, `mode="PLAY,RECORD"` being the
+/// reason a plain `split` will not do.
+fn split_quoted(s: &str, sep: u8) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, b) in s.bytes().enumerate() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == sep && !quoted {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn number<T: FromStr>(s: &str) -> Result<T> {
+    s.parse().map_err(|_| Error::Header)
+}
+
+fn pair<T: FromStr>(s: &str) -> Result<Pair<T>> {
+    match s.find('-') {
+        Some(i) => Ok((number(&s[..i])?, Some(number(&s[i + 1..])?))),
+        None => Ok((number(s)?, None))
+    }
+}
+
+fn write_pair<T: fmt::Display>(f: &mut fmt::Formatter, name: &str, pair: Option<Pair<T>>) -> fmt::Result {
+    match pair {
+        Some((first, Some(second))) => write!(f, ";{}={}-{}", name, first, second),
+        Some((first, None)) => write!(f, ";{}={}", name, first),
+        None => Ok(())
+    }
+}
+
+
+
+#[test]
+fn test() {
+    let transports: Transports = "RTP/AVP/TCP;unicast;interleaved=0-1, \
+                                  RTP/AVP;multicast;destination=224.2.0.1;ttl=127;port=3456-3457;mode=\"PLAY,RECORD\", \
+                                  RTP/SAVPF/UDP;unicast;client_port=5000-5001;server_port=6256-6257;\
+                                  source=10.0.0.1;ssrc=0A13C1F4;append;x-dynamic-rate=1".parse().unwrap();
+    let specs = &transports.0;
+    assert_eq!(specs.len(), 3);
+    assert_eq!(specs[0], Transport::tcp_interleaved(0));
+    assert!(specs[0].is_tcp());
+
+    assert!(specs[1].is_multicast());
+    assert_eq!(specs[1].destination, Some("224.2.0.1".to_string()));
+    assert_eq!(specs[1].ttl, Some(127));
+    assert_eq!(specs[1].port, Some((3456, Some(3457))));
+    assert_eq!(specs[1].mode, vec![Method::Play, Method::Record]);
+
+    assert_eq!(specs[2].profile, Profile::Savpf);
+    assert_eq!(specs[2].lower_transport, Some(LowerTransport::Udp));
+    assert_eq!(specs[2].client_port, Some((5000, Some(5001))));
+    assert_eq!(specs[2].server_port, Some((6256, Some(6257))));
+    assert_eq!(specs[2].source, Some("10.0.0.1".to_string()));
+    assert_eq!(specs[2].ssrc, Some(0x0A13C1F4));
+    assert!(specs[2].append);
+    assert_eq!(specs[2].extensions, vec![("x-dynamic-rate".to_string(), Some("1".to_string()))]);
+
+    let text = transports.to_string();
+    assert_eq!(text, "RTP/AVP/TCP;unicast;interleaved=0-1,\
+                      RTP/AVP;multicast;destination=224.2.0.1;ttl=127;port=3456-3457;mode=\"PLAY,RECORD\",\
+                      RTP/SAVPF/UDP;unicast;source=10.0.0.1;append;client_port=5000-5001;\
+                      server_port=6256-6257;ssrc=0A13C1F4;x-dynamic-rate=1");
+    assert_eq!(text.parse::<Transports>().unwrap(), transports);
+
+    assert_eq!(Transport::udp_unicast(8000).to_string(), "RTP/AVP;unicast;client_port=8000-8001");
+    assert!("RTP/AVP;client_port=abc".parse::<Transport>().is_err());
+    assert!("MP2T/H2221/UDP;unicast".parse::<Transport>().is_err());
+}
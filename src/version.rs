@@ -1,9 +1,13 @@
 
+
 use std::fmt;
+use std::str::FromStr;
+use error::Error;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy, Default)]
 pub enum RtspVersion {
     /// `RTSP/1.0`
+    #[default]
     Rtsp10
 }
 
@@ -15,10 +19,12 @@ impl fmt::Display for RtspVersion {
     }
 }
 
-
-impl Default for RtspVersion {
-    fn default() -> RtspVersion {
-        RtspVersion::Rtsp10
+impl FromStr for RtspVersion {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<RtspVersion, Error> {
+        match s {
+            "RTSP/1.0" => Ok(RtspVersion::Rtsp10),
+            _          => Err(Error::Version)
+        }
     }
 }
-